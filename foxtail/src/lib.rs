@@ -17,6 +17,9 @@ pub use glow;
 pub mod prelude;
 pub mod rendering;
 
+pub use rendering::state::CullSide;
+use rendering::state::{Blend, CompareFunc, FrontFace};
+
 #[cfg(target_os = "windows")]
 pub mod windows_perf_flags {
     #[no_mangle]
@@ -37,12 +40,6 @@ pub enum Fullscreen {
     Exclusive(VideoMode),
 }
 
-#[derive(Debug, PartialEq)]
-pub enum CullSide {
-    Front,
-    Back,
-}
-
 #[derive(Debug)]
 pub enum EngineEvent {
     SetTitle(String),
@@ -171,35 +168,22 @@ impl<'c> Context<'c> {
         self.video_modes
     }
 
+    #[deprecated(note = "Set `RenderState::blend` and use `Renderer::with_state` instead")]
     pub fn enable_alpha_blending(&self, enabled: bool) {
-        if enabled {
-            unsafe {
-                self.renderer.gl.enable(glow::BLEND);
-                self.renderer.gl.blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
-            }
-        } else {
-            unsafe { self.renderer.gl.disable(glow::BLEND); }
-        }
+        self.renderer.state.modify(|s| s.blend = if enabled { Some(Blend::ALPHA) } else { None });
     }
 
+    #[deprecated(note = "Set `RenderState::depth_test` and use `Renderer::with_state` instead")]
     pub fn enable_depth_buffer(&self, enabled: bool) {
-        if enabled {
-            unsafe { self.renderer.gl.enable(glow::DEPTH_TEST); }
-        } else {
-            unsafe { self.renderer.gl.disable(glow::DEPTH_TEST); }
-        }
+        self.renderer.state.modify(|s| s.depth_test = if enabled { Some(CompareFunc::Less) } else { None });
     }
 
+    #[deprecated(note = "Set `RenderState::cull` and use `Renderer::with_state` instead")]
     pub fn enable_backface_culling(&self, side: Option<CullSide>) {
-        if let Some(side) = side {
-            unsafe {
-                self.renderer.gl.enable(glow::CULL_FACE);
-                self.renderer.gl.cull_face(if side == CullSide::Front { glow::FRONT } else { glow::BACK });
-                self.renderer.gl.front_face(glow::CW);
-            }
-        } else {
-            unsafe { self.renderer.gl.disable(glow::CULL_FACE); }
-        }
+        self.renderer.state.modify(|s| {
+            s.cull = side;
+            s.front_face = FrontFace::Cw;
+        });
     }
 
     pub fn set_window_title<S: Into<String>>(&self, name: S) {
@@ -228,6 +212,8 @@ impl<'c> Context<'c> {
 
     pub fn draw_ui<F: FnMut(&foxtail_ui::EguiContext)>(&self, f: F) {
         self.fox_ui.draw(f);
+        // egui_glow sets its own blend, cull and scissor state
+        self.renderer.state.invalidate();
     }
 }
 
@@ -259,6 +245,11 @@ pub fn run<A: App + 'static, F: Fn(&Context) -> A>(f: F) {
             }
         }
 
+        if let Event::LoopDestroyed = event {
+            // The app and renderer get dropped after the last event, GL objects only get deleted with the context current
+            state.renderer.gl_make_current();
+        }
+
         let mut event_consumed = false;
         if let Event::WindowEvent { ref event, .. } = event {
             if state.fox_ui.event(&event) {
@@ -8,8 +8,26 @@ pub use crate::rendering::{
     buffer::*,
     atomic_counter::*,
     texture::*,
+    sampler::*,
+    layered_texture::*,
+    diagnostics::*,
+    barrier::*,
+    reflection::*,
+    spirv::*,
+    state::*,
+    vertex::*,
+    mesh_pool::*,
+    obj::*,
+    model::*,
+    mesh_data::*,
+    resources::*,
 };
 
+#[cfg(feature = "gltf")]
+pub use crate::rendering::scene::*;
+#[cfg(feature = "image")]
+pub use crate::rendering::texture_loader::*;
+
 pub use winit_input_helper::WinitInputHelper as Input;
 pub use winit::event::VirtualKeyCode as KeyCode;
 pub use winit::monitor::VideoMode;
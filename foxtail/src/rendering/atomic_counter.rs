@@ -1,14 +1,28 @@
 use std::sync::Arc;
 use glow::*;
 use crate::prelude::FixedSizeBuffer;
+use super::barrier::{BindingPoint, HazardTracker, MemoryBarrier, Resource};
+use super::resources::{ResourceKind, ResourceRegistry};
 
 pub struct AtomicCounter {
     buf: NativeBuffer,
     read_buf: FixedSizeBuffer<u32>,
     gl: Arc<Context>,
+    tracker: Arc<HazardTracker>,
+    resources: Arc<ResourceRegistry>,
     bound_loc: Option<u32>,
 }
 
+impl Drop for AtomicCounter {
+    fn drop(&mut self) {
+        self.tracker.forget(Resource::buffer(self.buf));
+        self.resources.unregister(ResourceKind::Buffer, self.buf.0.get());
+        unsafe {
+            self.gl.delete_buffer(self.buf);
+        }
+    }
+}
+
 impl AtomicCounter {
     pub fn new(renderer: &super::Renderer) -> Self {
         let gl = renderer.gl.clone();
@@ -20,11 +34,14 @@ impl AtomicCounter {
             b
         };
         // TODO: Make this storage host-only with flags?
-        let read_buf: FixedSizeBuffer<u32> = FixedSizeBuffer::new(renderer, 1);
+        let read_buf: FixedSizeBuffer<u32> = FixedSizeBuffer::new(renderer, 1).with_label("AtomicCounter readback");
+        renderer.resources.register(ResourceKind::Buffer, buf.0.get(), 4, "AtomicCounter");
         let obj = Self {
             buf: buf,
             read_buf,
             gl: gl,
+            tracker: renderer.tracker.clone(),
+            resources: renderer.resources.clone(),
             bound_loc: None,
         };
 
@@ -35,6 +52,7 @@ impl AtomicCounter {
 
     pub fn reset(&self, value: u32) {
         let bytes = value.to_le_bytes();
+        self.tracker.read(Resource::buffer(self.buf), MemoryBarrier::BUFFER_UPDATE);
         unsafe {
             self.gl.bind_buffer(glow::ATOMIC_COUNTER_BUFFER, Some(self.buf));
             self.gl.buffer_data_u8_slice(glow::ATOMIC_COUNTER_BUFFER, &bytes, glow::DYNAMIC_DRAW);
@@ -45,6 +63,7 @@ impl AtomicCounter {
     /// WARNING: Extremely slow!
     pub fn read(&self) -> u32 {
         let mut bytes = [0u8; 4];
+        self.tracker.read(Resource::buffer(self.buf), MemoryBarrier::BUFFER_UPDATE);
         unsafe {
             self.gl.bind_buffer(glow::ATOMIC_COUNTER_BUFFER, Some(self.buf));
             self.gl.bind_buffer(glow::SHADER_STORAGE_BUFFER, Some(self.read_buf.buf()));
@@ -59,11 +78,39 @@ impl AtomicCounter {
         u32::from_le_bytes(bytes)
     }
 
+    /// Copies the counter value into `dst` at `byte_offset`, without a round trip to the CPU.
+    /// Lets a count written on the GPU drive later passes, for example as the `num_groups_x`
+    /// of a `DispatchIndirectCommand` for a shader with a local size of 1 in x.
+    pub fn copy_to<T>(&self, dst: &FixedSizeBuffer<T>, byte_offset: usize) {
+        if byte_offset + 4 > dst.size() {
+            panic!("Cannot copy past buffer bounds!");
+        }
+        self.tracker.read(Resource::buffer(self.buf), MemoryBarrier::BUFFER_UPDATE);
+        unsafe {
+            self.gl.bind_buffer(glow::COPY_READ_BUFFER, Some(self.buf));
+            self.gl.bind_buffer(glow::COPY_WRITE_BUFFER, Some(dst.buf()));
+            self.gl.copy_buffer_sub_data(glow::COPY_READ_BUFFER, glow::COPY_WRITE_BUFFER, 0, byte_offset as i32, 4);
+            self.gl.bind_buffer(glow::COPY_WRITE_BUFFER, None);
+            self.gl.bind_buffer(glow::COPY_READ_BUFFER, None);
+        }
+    }
+
+    pub(crate) fn buf(&self) -> NativeBuffer {
+        self.buf
+    }
+
     pub fn bind(&mut self, location: u32) {
         self.bound_loc = Some(location);
+        self.bind_base(location);
+    }
+
+    /// Binds without remembering the location, for `UniformInterface::bind_atomic_counter`
+    pub(crate) fn bind_base(&self, location: u32) {
+        self.tracker.bind_read(BindingPoint::AtomicCounter(location), Resource::buffer(self.buf), MemoryBarrier::ATOMIC_COUNTER);
         unsafe {
             self.gl.bind_buffer_base(glow::ATOMIC_COUNTER_BUFFER, location, Some(self.buf));
         }
+        self.tracker.bind_write(BindingPoint::AtomicCounter(location), Resource::buffer(self.buf));
     }
 
     pub fn unbind(&mut self) {
@@ -71,6 +118,8 @@ impl AtomicCounter {
             unsafe {
                 self.gl.bind_buffer_base(glow::ATOMIC_COUNTER_BUFFER, loc, None);
             }
+            self.tracker.unbind_write(BindingPoint::AtomicCounter(loc));
+            self.tracker.unbind_read(BindingPoint::AtomicCounter(loc), None);
             self.bound_loc = None;
         } else {
             trace!("Attempting to unbind unbound buffer!");
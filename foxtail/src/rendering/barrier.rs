@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::ops::{BitOr, BitOrAssign};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use glow::*;
+
+/// Flags for `Renderer::barrier`. Each one makes incoherent shader writes (SSBOs, image stores,
+/// atomic counters) visible to a specific kind of access after the barrier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemoryBarrier(u32);
+
+impl MemoryBarrier {
+    pub const VERTEX_ATTRIB_ARRAY: Self = Self(VERTEX_ATTRIB_ARRAY_BARRIER_BIT);
+    pub const ELEMENT_ARRAY: Self = Self(ELEMENT_ARRAY_BARRIER_BIT);
+    pub const UNIFORM: Self = Self(UNIFORM_BARRIER_BIT);
+    pub const TEXTURE_FETCH: Self = Self(TEXTURE_FETCH_BARRIER_BIT);
+    pub const SHADER_IMAGE_ACCESS: Self = Self(SHADER_IMAGE_ACCESS_BARRIER_BIT);
+    pub const COMMAND: Self = Self(COMMAND_BARRIER_BIT);
+    pub const PIXEL_BUFFER: Self = Self(PIXEL_BUFFER_BARRIER_BIT);
+    pub const TEXTURE_UPDATE: Self = Self(TEXTURE_UPDATE_BARRIER_BIT);
+    pub const BUFFER_UPDATE: Self = Self(BUFFER_UPDATE_BARRIER_BIT);
+    pub const FRAMEBUFFER: Self = Self(FRAMEBUFFER_BARRIER_BIT);
+    pub const TRANSFORM_FEEDBACK: Self = Self(TRANSFORM_FEEDBACK_BARRIER_BIT);
+    pub const ATOMIC_COUNTER: Self = Self(ATOMIC_COUNTER_BARRIER_BIT);
+    pub const SHADER_STORAGE: Self = Self(SHADER_STORAGE_BARRIER_BIT);
+    pub const CLIENT_MAPPED_BUFFER: Self = Self(CLIENT_MAPPED_BUFFER_BARRIER_BIT);
+    pub const QUERY_BUFFER: Self = Self(QUERY_BUFFER_BARRIER_BIT);
+    pub const ALL: Self = Self(ALL_BARRIER_BITS);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for MemoryBarrier {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for MemoryBarrier {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Resource {
+    Buffer(u32),
+    Texture(u32),
+}
+
+impl Resource {
+    pub(crate) fn buffer(buf: NativeBuffer) -> Self {
+        Self::Buffer(buf.0.get())
+    }
+
+    pub(crate) fn texture(tex: NativeTexture) -> Self {
+        Self::Texture(tex.0.get())
+    }
+
+    /// The GL name of the buffer or texture
+    pub(crate) fn name(self) -> u32 {
+        match self {
+            Self::Buffer(name) | Self::Texture(name) => name,
+        }
+    }
+}
+
+/// Indexed binding points shaders access resources through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum BindingPoint {
+    Storage(u32),
+    AtomicCounter(u32),
+    Image(u32),
+    /// Texture unit, read only
+    Texture(u32),
+}
+
+#[derive(Default)]
+struct HazardState {
+    /// Resources currently bound somewhere shaders can write to them
+    bound_writes: HashMap<BindingPoint, Resource>,
+    /// Resources currently bound somewhere shaders read them, with the barrier the read needs
+    bound_reads: HashMap<BindingPoint, (Resource, MemoryBarrier)>,
+    /// Resources written by a draw or dispatch, with the barriers issued since
+    written: HashMap<Resource, MemoryBarrier>,
+}
+
+/// Records which resources the GPU wrote to, and issues the barrier a later read needs.
+/// Off by default, see `Renderer::enable_hazard_tracking`.
+pub(crate) struct HazardTracker {
+    enabled: AtomicBool,
+    state: Mutex<HazardState>,
+    gl: Arc<Context>,
+}
+
+impl HazardTracker {
+    pub(crate) fn new(gl: Arc<Context>) -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            state: Mutex::new(HazardState::default()),
+            gl,
+        }
+    }
+
+    pub(crate) fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Release);
+        if !enabled {
+            *self.state.lock().unwrap() = HazardState::default();
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    /// Issues a barrier and marks it as issued for everything written so far
+    pub(crate) fn barrier(&self, barrier: MemoryBarrier) {
+        unsafe { self.gl.memory_barrier(barrier.bits()); }
+        if self.enabled() {
+            let mut state = self.state.lock().unwrap();
+            if barrier == MemoryBarrier::ALL {
+                state.written.clear();
+            } else {
+                for issued in state.written.values_mut() {
+                    *issued |= barrier;
+                }
+            }
+        }
+    }
+
+    /// Call before `res` gets accessed in a way that needs `barrier` after a shader write
+    pub(crate) fn read(&self, res: Resource, barrier: MemoryBarrier) {
+        if !self.enabled() {
+            return;
+        }
+        let needed = self.state.lock().unwrap().written.get(&res).is_some_and(|issued| !issued.contains(barrier));
+        if needed {
+            trace!("Inserting memory barrier {:?} for {:?}", barrier, res);
+            self.barrier(barrier);
+        }
+    }
+
+    /// Call when `res` gets bound somewhere shaders read it. Reads `res` now, and again before every
+    /// draw or dispatch while it stays bound, in case something else writes it in the meantime.
+    /// Returns what was bound there before, for `unbind_read`.
+    pub(crate) fn bind_read(&self, point: BindingPoint, res: Resource, barrier: MemoryBarrier) -> Option<(Resource, MemoryBarrier)> {
+        if !self.enabled() {
+            return None;
+        }
+        self.read(res, barrier);
+        self.state.lock().unwrap().bound_reads.insert(point, (res, barrier))
+    }
+
+    /// Call when a binding point is cleared, `previous` is bound there again
+    pub(crate) fn unbind_read(&self, point: BindingPoint, previous: Option<(Resource, MemoryBarrier)>) {
+        if self.enabled() {
+            let mut state = self.state.lock().unwrap();
+            match previous {
+                Some(previous) => state.bound_reads.insert(point, previous),
+                None => state.bound_reads.remove(&point),
+            };
+        }
+    }
+
+    /// Call when `res` gets bound somewhere shaders can write to it
+    pub(crate) fn bind_write(&self, point: BindingPoint, res: Resource) {
+        if self.enabled() {
+            self.state.lock().unwrap().bound_writes.insert(point, res);
+        }
+    }
+
+    /// Call when a binding point is cleared, or gets something bound read-only
+    pub(crate) fn unbind_write(&self, point: BindingPoint) {
+        if self.enabled() {
+            self.state.lock().unwrap().bound_writes.remove(&point);
+        }
+    }
+
+    /// Call before every draw or dispatch, issues the barriers bound resources need since they were bound
+    pub(crate) fn issuing_commands(&self) {
+        if !self.enabled() {
+            return;
+        }
+        let needed = {
+            let state = self.state.lock().unwrap();
+            state.bound_reads.values()
+                .filter(|(res, barrier)| state.written.get(res).is_some_and(|issued| !issued.contains(*barrier)))
+                .fold(MemoryBarrier::empty(), |needed, (_, barrier)| needed | *barrier)
+        };
+        if !needed.is_empty() {
+            trace!("Inserting memory barrier {:?} for bound resources", needed);
+            self.barrier(needed);
+        }
+    }
+
+    /// Call after every draw or dispatch, everything bound for writing may have been written
+    pub(crate) fn commands_issued(&self) {
+        if self.enabled() {
+            let mut state = self.state.lock().unwrap();
+            let HazardState { bound_writes, written, .. } = &mut *state;
+            for res in bound_writes.values() {
+                written.insert(*res, MemoryBarrier::empty());
+            }
+        }
+    }
+
+    /// Call when a resource is deleted, GL may hand out its name again
+    pub(crate) fn forget(&self, res: Resource) {
+        if self.enabled() {
+            let mut state = self.state.lock().unwrap();
+            state.bound_writes.retain(|_, bound| *bound != res);
+            state.bound_reads.retain(|_, bound| bound.0 != res);
+            state.written.remove(&res);
+        }
+    }
+}
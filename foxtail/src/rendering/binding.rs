@@ -0,0 +1,155 @@
+//! Stacks of what is currently bound, so nested `while_bound` calls restore the outer binding
+//! instead of unbinding everything. Each level is popped by a guard, so early returns and panics restore it too.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use glow::*;
+
+struct ProgramLevel {
+    program: NativeProgram,
+    patch_vertices: Option<i32>,
+}
+
+struct FramebufferLevel {
+    fbo: Option<NativeFramebuffer>,
+    /// Viewport to go back to when this level is popped
+    previous_viewport: [i32; 4],
+}
+
+#[derive(Clone, Copy)]
+struct TextureLevel {
+    target: u32,
+    tex: NativeTexture,
+}
+
+enum Level {
+    Program,
+    Framebuffer,
+    TextureUnit(u32),
+    Sampler(u32),
+}
+
+pub(crate) struct BindingStack {
+    programs: Mutex<Vec<ProgramLevel>>,
+    framebuffers: Mutex<Vec<FramebufferLevel>>,
+    texture_units: Mutex<HashMap<u32, Vec<TextureLevel>>>,
+    samplers: Mutex<HashMap<u32, Vec<NativeSampler>>>,
+    gl: Arc<Context>,
+}
+
+/// Guards get dropped while unwinding, a poisoned lock shouldn't turn that into an abort
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl BindingStack {
+    pub(crate) fn new(gl: Arc<Context>) -> Self {
+        Self {
+            programs: Mutex::new(Vec::new()),
+            framebuffers: Mutex::new(Vec::new()),
+            texture_units: Mutex::new(HashMap::new()),
+            samplers: Mutex::new(HashMap::new()),
+            gl,
+        }
+    }
+
+    pub(crate) fn program_bound(&self) -> bool {
+        !lock(&self.programs).is_empty()
+    }
+
+    unsafe fn use_program(&self, level: Option<&ProgramLevel>) {
+        self.gl.use_program(level.map(|l| l.program));
+        if let Some(count) = level.and_then(|l| l.patch_vertices) {
+            self.gl.patch_parameter_i32(PATCH_VERTICES, count);
+        }
+    }
+
+    pub(crate) fn push_program(self: &Arc<Self>, program: NativeProgram, patch_vertices: Option<i32>) -> BindingGuard {
+        let level = ProgramLevel { program, patch_vertices };
+        unsafe { self.use_program(Some(&level)); }
+        lock(&self.programs).push(level);
+        BindingGuard { stack: self.clone(), level: Level::Program }
+    }
+
+    /// Binds `fbo` (`None` for the default framebuffer) with a viewport of `viewport`
+    pub(crate) fn push_framebuffer(self: &Arc<Self>, fbo: Option<NativeFramebuffer>, viewport: [i32; 4]) -> BindingGuard {
+        let mut previous_viewport = [0i32; 4];
+        unsafe {
+            self.gl.get_parameter_i32_slice(VIEWPORT, &mut previous_viewport);
+            self.gl.bind_framebuffer(FRAMEBUFFER, fbo);
+            self.gl.viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+        lock(&self.framebuffers).push(FramebufferLevel { fbo, previous_viewport });
+        BindingGuard { stack: self.clone(), level: Level::Framebuffer }
+    }
+
+    pub(crate) fn push_texture(self: &Arc<Self>, unit: u32, target: u32, tex: NativeTexture) -> BindingGuard {
+        unsafe {
+            self.gl.active_texture(TEXTURE0 + unit);
+            self.gl.bind_texture(target, Some(tex));
+        }
+        lock(&self.texture_units).entry(unit).or_default().push(TextureLevel { target, tex });
+        BindingGuard { stack: self.clone(), level: Level::TextureUnit(unit) }
+    }
+
+    pub(crate) fn push_sampler(self: &Arc<Self>, unit: u32, sampler: NativeSampler) -> BindingGuard {
+        unsafe {
+            self.gl.bind_sampler(unit, Some(sampler));
+        }
+        lock(&self.samplers).entry(unit).or_default().push(sampler);
+        BindingGuard { stack: self.clone(), level: Level::Sampler(unit) }
+    }
+
+    fn pop(&self, level: &Level) {
+        unsafe {
+            match level {
+                Level::Program => {
+                    let mut programs = lock(&self.programs);
+                    programs.pop();
+                    self.use_program(programs.last());
+                },
+                Level::Framebuffer => {
+                    let mut framebuffers = lock(&self.framebuffers);
+                    if let Some(popped) = framebuffers.pop() {
+                        self.gl.bind_framebuffer(FRAMEBUFFER, framebuffers.last().and_then(|l| l.fbo));
+                        let [x, y, width, height] = popped.previous_viewport;
+                        self.gl.viewport(x, y, width, height);
+                    }
+                },
+                Level::TextureUnit(unit) => {
+                    let mut units = lock(&self.texture_units);
+                    let stack = units.entry(*unit).or_default();
+                    let popped = stack.pop();
+                    let previous = stack.last().copied();
+                    self.gl.active_texture(TEXTURE0 + unit);
+                    if let Some(popped) = popped {
+                        if previous.is_none_or(|p| p.target != popped.target) {
+                            self.gl.bind_texture(popped.target, None);
+                        }
+                    }
+                    if let Some(previous) = previous {
+                        self.gl.bind_texture(previous.target, Some(previous.tex));
+                    }
+                },
+                Level::Sampler(unit) => {
+                    let mut samplers = lock(&self.samplers);
+                    let stack = samplers.entry(*unit).or_default();
+                    stack.pop();
+                    self.gl.bind_sampler(*unit, stack.last().copied());
+                },
+            }
+        }
+    }
+}
+
+/// Restores the previous binding when dropped
+#[must_use]
+pub(crate) struct BindingGuard {
+    stack: Arc<BindingStack>,
+    level: Level,
+}
+
+impl Drop for BindingGuard {
+    fn drop(&mut self) {
+        self.stack.pop(&self.level);
+    }
+}
@@ -1,22 +1,36 @@
 use std::sync::Arc;
 use glow::*;
 
-#[derive(Clone)]
+use super::barrier::{BindingPoint, HazardTracker, MemoryBarrier, Resource};
+use super::resources::{ResourceKind, ResourceRegistry};
+
 pub struct FixedSizeBuffer<T> {
     buf: NativeBuffer,
     size: usize,
     gl: Arc<Context>,
+    tracker: Arc<HazardTracker>,
+    resources: Arc<ResourceRegistry>,
     bound_loc: Option<u32>,
     _phantom: std::marker::PhantomData<T>,
 }
 
+impl<T> Drop for FixedSizeBuffer<T> {
+    fn drop(&mut self) {
+        self.tracker.forget(Resource::buffer(self.buf));
+        self.resources.unregister(ResourceKind::Buffer, self.buf.0.get());
+        unsafe {
+            self.gl.delete_buffer(self.buf);
+        }
+    }
+}
+
 impl<T> FixedSizeBuffer<T> {
     pub fn new(renderer: &super::Renderer, count: usize) -> Self {
         let gl = renderer.gl.clone();
-        Self::new_from_gl(gl, count)
+        Self::new_from_gl(gl, renderer.tracker.clone(), renderer.resources.clone(), count)
     }
 
-    pub(crate) fn new_from_gl(gl: Arc<Context>, count: usize) -> Self {
+    pub(crate) fn new_from_gl(gl: Arc<Context>, tracker: Arc<HazardTracker>, resources: Arc<ResourceRegistry>, count: usize) -> Self {
         let size = std::mem::size_of::<T>() * count;
         trace!("Allocating buffer with size: {}b/{}kb/{}mb", size, size/1024, size/1024/1024);
         let buf = unsafe { gl.create_buffer().expect("Failed to create buffer!") };
@@ -24,15 +38,24 @@ impl<T> FixedSizeBuffer<T> {
             buf: buf,
             size: size,
             gl: gl,
+            tracker,
+            resources,
             bound_loc: None,
             _phantom: std::marker::PhantomData,
         };
 
         obj.alloc_buffer();
+        obj.resources.register(ResourceKind::Buffer, buf.0.get(), size, &format!("FixedSizeBuffer<{}>", std::any::type_name::<T>()));
 
         obj
     }
 
+    /// Replaces the label shown in the resource registry and graphics debuggers
+    pub fn with_label(self, label: &str) -> Self {
+        self.resources.set_label(ResourceKind::Buffer, self.buf.0.get(), label);
+        self
+    }
+
     fn alloc_buffer(&self) {
         let zero_data = vec![0u8; self.size];
         unsafe {
@@ -49,6 +72,7 @@ impl<T> FixedSizeBuffer<T> {
         if offset_raw + data.as_ref().len() * t_size > self.size {
             panic!("Cannot write past buffer bounds!");
         }
+        self.tracker.read(Resource::buffer(self.buf), MemoryBarrier::BUFFER_UPDATE);
         unsafe {
             let data_raw: &[u8] = std::slice::from_raw_parts(
                 data.as_ref().as_ptr() as *const u8,
@@ -62,6 +86,7 @@ impl<T> FixedSizeBuffer<T> {
     }
 
     pub fn write_slice<'f>(&'f self, writes: impl Iterator<Item = (usize, &'f T)>) {
+        self.tracker.read(Resource::buffer(self.buf), MemoryBarrier::BUFFER_UPDATE);
         unsafe { self.gl.bind_buffer(glow::SHADER_STORAGE_BUFFER, Some(self.buf)); }
         for (offset, data) in writes {
             let t_size = std::mem::size_of::<T>();
@@ -82,6 +107,7 @@ impl<T> FixedSizeBuffer<T> {
     }
 
     pub fn clear(&self) {
+        self.tracker.read(Resource::buffer(self.buf), MemoryBarrier::BUFFER_UPDATE);
         unsafe {
             self.gl.bind_buffer(glow::SHADER_STORAGE_BUFFER, Some(self.buf));
             self.gl.invalidate_buffer_sub_data(glow::SHADER_STORAGE_BUFFER, 0, self.size as i32);
@@ -100,9 +126,16 @@ impl<T> FixedSizeBuffer<T> {
 
     pub fn bind(&mut self, location: u32) {
         self.bound_loc = Some(location);
+        self.bind_base(location);
+    }
+
+    /// Binds without remembering the location, for `UniformInterface::bind_storage`
+    pub(crate) fn bind_base(&self, location: u32) {
+        self.tracker.bind_read(BindingPoint::Storage(location), Resource::buffer(self.buf), MemoryBarrier::SHADER_STORAGE);
         unsafe {
             self.gl.bind_buffer_base(glow::SHADER_STORAGE_BUFFER, location, Some(self.buf));
         }
+        self.tracker.bind_write(BindingPoint::Storage(location), Resource::buffer(self.buf));
     }
 
     pub fn unbind(&mut self) {
@@ -110,6 +143,8 @@ impl<T> FixedSizeBuffer<T> {
             unsafe {
                 self.gl.bind_buffer_base(glow::SHADER_STORAGE_BUFFER, loc, None);
             }
+            self.tracker.unbind_write(BindingPoint::Storage(loc));
+            self.tracker.unbind_read(BindingPoint::Storage(loc), None);
             self.bound_loc = None;
         } else {
             trace!("Attempting to unbind unbound buffer!");
@@ -0,0 +1,434 @@
+//! Parsing for shader compiler logs. Every driver formats these differently:
+//! - NVIDIA:               `0(12) : error C1503: undefined variable "foo"`
+//! - Mesa:                 `0:12(5): error: 'foo' undeclared`
+//! - AMD, Intel, glslang:  `ERROR: 0:12: 'foo' : undeclared identifier`
+//! - Older AMD:            `ERROR: 0:12: error(#143) Undeclared identifier: foo`
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        if s.contains("error") {
+            Some(Self::Error)
+        } else if s.contains("warning") {
+            Some(Self::Warning)
+        } else if s == "info" || s == "note" {
+            Some(Self::Info)
+        } else {
+            None
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Info => "info",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderDiagnostic {
+    /// Source string index or file name, as reported by the compiler
+    pub file: Option<String>,
+    /// 1-based
+    pub line: Option<usize>,
+    /// 1-based
+    pub column: Option<usize>,
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        if let Some(line) = self.line {
+            write!(f, "{}:", line)?;
+        }
+        if let Some(column) = self.column {
+            write!(f, "{}:", column)?;
+        }
+        if self.file.is_some() || self.line.is_some() {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", self.severity.as_str())?;
+        if let Some(code) = &self.code {
+            write!(f, "[{}]", code)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Parses `digits` from the start of `s`, returning the number and the remainder
+fn parse_number(s: &str) -> Option<(usize, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if end == 0 {
+        return None;
+    }
+    Some((s[..end].parse().ok()?, &s[end..]))
+}
+
+/// Parses `file:line:` or `file:line(column):` (Mesa).
+/// Tries every colon, so file names with colons in them (`C:\shader.vert`) work too.
+fn parse_colon_location(s: &str) -> Option<(String, usize, Option<usize>, &str)> {
+    for (i, _) in s.match_indices(':') {
+        let file = &s[..i];
+        if file.is_empty() || file.contains(char::is_whitespace) {
+            continue;
+        }
+        let (line, rest) = match parse_number(&s[i + 1..]) {
+            Some(l) => l,
+            None => continue,
+        };
+        if let Some(rest) = rest.strip_prefix(':') {
+            return Some((file.to_string(), line, None, rest));
+        }
+        if let Some((column, rest)) = rest.strip_prefix('(').and_then(parse_number) {
+            if let Some(rest) = rest.strip_prefix("):") {
+                return Some((file.to_string(), line, Some(column), rest));
+            }
+        }
+    }
+    None
+}
+
+/// Parses `file(line) :` (NVIDIA)
+fn parse_paren_location(s: &str) -> Option<(String, usize, &str)> {
+    let open = s.find('(')?;
+    let file = &s[..open];
+    if file.is_empty() || file.contains(char::is_whitespace) {
+        return None;
+    }
+    let (line, rest) = parse_number(&s[open + 1..])?;
+    let rest = rest.strip_prefix(')')?.trim_start().strip_prefix(':')?;
+    Some((file.to_string(), line, rest))
+}
+
+/// Splits `error C1503: message` or `error: message` into severity, code and message
+fn parse_severity_code(s: &str) -> Option<(Severity, Option<String>, String)> {
+    let (head, message) = s.split_once(':')?;
+    let head = head.trim();
+    let (severity, code) = match head.rsplit_once(' ') {
+        Some((sev, code)) if Severity::parse(sev).is_some() && !code.is_empty() => (Severity::parse(sev)?, Some(code.to_string())),
+        _ => (Severity::parse(head)?, None),
+    };
+    Some((severity, code, message.trim().to_string()))
+}
+
+/// Splits off an `error(#143)` style code from the start of a message
+fn split_hash_code(message: &str) -> (Option<String>, String) {
+    if let Some(start) = message.find("(#") {
+        let head = &message[..start];
+        if Severity::parse(head).is_some() {
+            if let Some(end) = message[start..].find(')') {
+                let code = &message[start + 1..start + end];
+                return (Some(code.to_string()), message[start + end + 1..].trim().to_string());
+            }
+        }
+    }
+    (None, message.to_string())
+}
+
+fn parse_line(line: &str) -> Option<ShaderDiagnostic> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    // `ERROR: 0:12: message`
+    if let Some((prefix, rest)) = line.split_once(": ") {
+        if prefix.chars().all(|c| c.is_ascii_uppercase()) {
+            if let Some(severity) = Severity::parse(prefix) {
+                if let Some((file, line, column, rest)) = parse_colon_location(rest.trim_start()) {
+                    let (code, message) = split_hash_code(rest.trim());
+                    return Some(ShaderDiagnostic {
+                        file: Some(file),
+                        line: Some(line),
+                        column,
+                        severity,
+                        code,
+                        message,
+                    });
+                }
+                // Summaries like `ERROR: 1 compilation errors.  No code generated.`
+                return Some(ShaderDiagnostic {
+                    file: None,
+                    line: None,
+                    column: None,
+                    severity,
+                    code: None,
+                    message: rest.trim().to_string(),
+                });
+            }
+        }
+    }
+
+    // `0:12(5): error: message`, checked first as the file part could look like `0:12`
+    if let Some((file, line, column, rest)) = parse_colon_location(line) {
+        if let Some((severity, code, message)) = parse_severity_code(rest) {
+            return Some(ShaderDiagnostic {
+                file: Some(file),
+                line: Some(line),
+                column,
+                severity,
+                code,
+                message,
+            });
+        }
+    }
+
+    // `0(12) : error C1503: message`
+    if let Some((file, line, rest)) = parse_paren_location(line) {
+        if let Some((severity, code, message)) = parse_severity_code(rest) {
+            return Some(ShaderDiagnostic {
+                file: Some(file),
+                line: Some(line),
+                column: None,
+                severity,
+                code,
+                message,
+            });
+        }
+    }
+
+    // Unlocated messages, like Mesa's link errors: `error: vertex shader output ... not consumed`
+    for (prefix, severity) in [("error:", Severity::Error), ("warning:", Severity::Warning)] {
+        if line.get(..prefix.len()).is_some_and(|p| p.eq_ignore_ascii_case(prefix)) {
+            return Some(ShaderDiagnostic {
+                file: None,
+                line: None,
+                column: None,
+                severity,
+                code: None,
+                message: line[prefix.len()..].trim().to_string(),
+            });
+        }
+    }
+
+    // Anything else is kept, so no information gets lost
+    Some(ShaderDiagnostic {
+        file: None,
+        line: None,
+        column: None,
+        severity: Severity::Info,
+        code: None,
+        message: line.to_string(),
+    })
+}
+
+/// Parses a shader info log into a list of diagnostics
+pub fn parse_shader_log(log: &str) -> Vec<ShaderDiagnostic> {
+    log.lines().filter_map(parse_line).collect()
+}
+
+/// Identifiers quoted in a message, like `'foo'`, `"foo"` or Mesa's `` `foo' ``
+fn quoted_identifiers(message: &str) -> Vec<&str> {
+    let mut idents = Vec::new();
+    let mut rest = message;
+    while let Some(start) = rest.find(['\'', '"', '`']) {
+        let close = if rest[start..].starts_with('"') { '"' } else { '\'' };
+        let after = &rest[start + 1..];
+        let end = match after.find(close) {
+            Some(end) => end,
+            None => break,
+        };
+        let ident = &after[..end];
+        if !ident.is_empty() && ident.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            idents.push(ident);
+        }
+        rest = &after[end + 1..];
+    }
+    idents
+}
+
+/// Wraps whole-word occurrences of `ident` in `line` in a highlight color
+fn highlight_word(line: &str, ident: &str) -> String {
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut out = String::new();
+    let mut last = 0;
+    for (i, _) in line.match_indices(ident) {
+        let before = line[..i].chars().next_back();
+        let after = line[i + ident.len()..].chars().next();
+        if before.is_some_and(is_ident_char) || after.is_some_and(is_ident_char) {
+            continue;
+        }
+        out.push_str(&line[last..i]);
+        out.push_str(&format!("\x1b[1;33m{}\x1b[22;0m", ident));
+        last = i + ident.len();
+    }
+    out.push_str(&line[last..]);
+    out
+}
+
+/// Renders diagnostics with colored snippets of the source around each one
+pub fn format_diagnostics(src: &str, diagnostics: &[ShaderDiagnostic]) -> String {
+    let src_split = src.lines().collect::<Vec<&str>>();
+    let mut formatted_errors = String::new();
+    for diag in diagnostics {
+        formatted_errors.push_str(match diag.severity {
+            Severity::Error => "\x1b[1;31m",
+            Severity::Warning => "\x1b[1;33m",
+            Severity::Info => "\x1b[1;36m",
+        });
+        formatted_errors.push_str(&diag.to_string());
+        formatted_errors.push_str("\x1b[0m");
+        formatted_errors.push('\n');
+
+        let line_number = match diag.line {
+            Some(line) if line > 0 => line - 1,
+            _ => continue,
+        };
+        let highlights = quoted_identifiers(&diag.message);
+        let min = line_number.saturating_sub(1);
+        let max = line_number + 1;
+        let line_num_str = (line_number + 1).to_string();
+        for i in min..=max {
+            formatted_errors.push_str("\x1b[1;36m");
+            if i == line_number {
+                formatted_errors.push_str(&format!(" {} | ", line_num_str));
+            } else {
+                for _ in 0..(line_num_str.len()+2) { formatted_errors.push(' '); }
+                formatted_errors.push_str("| ");
+            }
+            formatted_errors.push_str("\x1b[22;0m");
+            let mut code_line = src_split.get(i).copied().unwrap_or("CODE NOT FOUND").to_string();
+            for ident in &highlights {
+                code_line = highlight_word(&code_line, ident);
+            }
+            formatted_errors.push_str(&code_line);
+            if i == line_number {
+                formatted_errors.push_str(match diag.severity {
+                    Severity::Error => "\x1b[1;31m <- Error occurs here\x1b[22;0m",
+                    Severity::Warning => "\x1b[1;33m <- Warning occurs here\x1b[22;0m",
+                    Severity::Info => "\x1b[1;36m <- Note\x1b[22;0m",
+                });
+            }
+            formatted_errors.push('\n');
+            if i == line_number {
+                if let Some(column) = diag.column {
+                    for _ in 0..(line_num_str.len()+2) { formatted_errors.push(' '); }
+                    formatted_errors.push_str("\x1b[1;36m| \x1b[1;31m");
+                    for _ in 1..column { formatted_errors.push(' '); }
+                    formatted_errors.push_str("^\x1b[22;0m\n");
+                }
+            }
+        }
+        formatted_errors.push('\n');
+    }
+    formatted_errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diag(file: &str, line: usize, column: Option<usize>, severity: Severity, code: Option<&str>, message: &str) -> ShaderDiagnostic {
+        ShaderDiagnostic {
+            file: Some(file.to_string()),
+            line: Some(line),
+            column,
+            severity,
+            code: code.map(|c| c.to_string()),
+            message: message.to_string(),
+        }
+    }
+
+    fn unlocated(severity: Severity, message: &str) -> ShaderDiagnostic {
+        ShaderDiagnostic {
+            file: None,
+            line: None,
+            column: None,
+            severity,
+            code: None,
+            message: message.to_string(),
+        }
+    }
+
+    fn strip_colors(s: &str) -> String {
+        let mut out = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn nvidia() {
+        let log = "0(12) : error C1503: undefined variable \"foo\"\n0(3) : warning C7022: unrecognized profile specifier \"bar\"\n";
+        assert_eq!(parse_shader_log(log), vec![
+            diag("0", 12, None, Severity::Error, Some("C1503"), "undefined variable \"foo\""),
+            diag("0", 3, None, Severity::Warning, Some("C7022"), "unrecognized profile specifier \"bar\""),
+        ]);
+    }
+
+    #[test]
+    fn mesa() {
+        let log = "0:12(5): error: `foo' undeclared\n0:14(10): warning: `bar' used uninitialized\n";
+        assert_eq!(parse_shader_log(log), vec![
+            diag("0", 12, Some(5), Severity::Error, None, "`foo' undeclared"),
+            diag("0", 14, Some(10), Severity::Warning, None, "`bar' used uninitialized"),
+        ]);
+    }
+
+    #[test]
+    fn mesa_link_error() {
+        let log = "error: vertex shader output `v_uv' not consumed by fragment shader\n";
+        assert_eq!(parse_shader_log(log), vec![
+            unlocated(Severity::Error, "vertex shader output `v_uv' not consumed by fragment shader"),
+        ]);
+    }
+
+    #[test]
+    fn amd() {
+        let log = "ERROR: 2:15: error(#143) Undeclared identifier: foo\nERROR: error(#273) 1 compilation errors.  No code generated\n";
+        assert_eq!(parse_shader_log(log), vec![
+            diag("2", 15, None, Severity::Error, Some("#143"), "Undeclared identifier: foo"),
+            unlocated(Severity::Error, "error(#273) 1 compilation errors.  No code generated"),
+        ]);
+    }
+
+    #[test]
+    fn intel() {
+        let log = "ERROR: 0:12: 'foo' : undeclared identifier\nWARNING: 0:7: 'bar' : unused variable\nERROR: 1 compilation errors.  No code generated.\n";
+        assert_eq!(parse_shader_log(log), vec![
+            diag("0", 12, None, Severity::Error, None, "'foo' : undeclared identifier"),
+            diag("0", 7, None, Severity::Warning, None, "'bar' : unused variable"),
+            unlocated(Severity::Error, "1 compilation errors.  No code generated."),
+        ]);
+    }
+
+    #[test]
+    fn unknown_lines_are_kept() {
+        assert_eq!(parse_shader_log("Vertex info\n-----------\n"), vec![
+            unlocated(Severity::Info, "Vertex info"),
+            unlocated(Severity::Info, "-----------"),
+        ]);
+    }
+
+    #[test]
+    fn format_points_at_line() {
+        let src = "#version 450\nvoid main() {\n    float x = foo;\n}\n";
+        let formatted = strip_colors(&format_diagnostics(src, &[diag("0", 3, Some(15), Severity::Error, None, "'foo' : undeclared identifier")]));
+        let lines = formatted.lines().collect::<Vec<&str>>();
+        assert_eq!(lines[0], "0:3:15: error: 'foo' : undeclared identifier");
+        assert_eq!(lines[1], "   | void main() {");
+        assert_eq!(lines[2], " 3 |     float x = foo; <- Error occurs here");
+        assert_eq!(lines[3], "   |               ^");
+        assert_eq!(lines[4], "   | }");
+    }
+}
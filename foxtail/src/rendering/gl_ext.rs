@@ -0,0 +1,183 @@
+//! OpenGL entry points that glow 0.12 does not wrap, loaded straight from the context.
+//! glow can't be bumped without also bumping egui_glow, so these live here for now.
+use std::ffi::c_void;
+use glow::*;
+
+type GetProgramIv = unsafe extern "system" fn(program: u32, pname: u32, params: *mut i32);
+type GetProgramBinary = unsafe extern "system" fn(program: u32, buf_size: i32, length: *mut i32, binary_format: *mut u32, binary: *mut c_void);
+type ProgramBinary = unsafe extern "system" fn(program: u32, binary_format: u32, binary: *const c_void, length: i32);
+type ProgramParameterI = unsafe extern "system" fn(program: u32, pname: u32, value: i32);
+type GetProgramInterfaceIv = unsafe extern "system" fn(program: u32, interface: u32, pname: u32, params: *mut i32);
+type GetProgramResourceName = unsafe extern "system" fn(program: u32, interface: u32, index: u32, buf_size: i32, length: *mut i32, name: *mut u8);
+type ShaderBinary = unsafe extern "system" fn(count: i32, shaders: *const u32, binary_format: u32, binary: *const c_void, length: i32);
+type SpecializeShader = unsafe extern "system" fn(shader: u32, entry_point: *const std::os::raw::c_char, num_constants: u32, constant_index: *const u32, constant_value: *const u32);
+type MultiDrawElementsIndirect = unsafe extern "system" fn(mode: u32, element_type: u32, indirect: *const c_void, draw_count: i32, stride: i32);
+type MultiDrawElementsIndirectCount = unsafe extern "system" fn(mode: u32, element_type: u32, indirect: *const c_void, draw_count: isize, max_draw_count: i32, stride: i32);
+type BindImageTexture = unsafe extern "system" fn(unit: u32, texture: u32, level: i32, layered: u8, layer: i32, access: u32, format: u32);
+type TextureSubImage2D = unsafe extern "system" fn(texture: u32, level: i32, x_offset: i32, y_offset: i32, width: i32, height: i32, format: u32, ty: u32, pixels: *const c_void);
+type GetTextureImage = unsafe extern "system" fn(texture: u32, level: i32, format: u32, ty: u32, buf_size: i32, pixels: *mut c_void);
+type GetProgramResourceIv = unsafe extern "system" fn(program: u32, interface: u32, index: u32, prop_count: i32, props: *const u32, buf_size: i32, length: *mut i32, params: *mut i32);
+
+pub(crate) struct GlExt {
+    get_program_iv: Option<GetProgramIv>,
+    get_program_binary: Option<GetProgramBinary>,
+    program_binary: Option<ProgramBinary>,
+    program_parameter_i: Option<ProgramParameterI>,
+    get_program_interface_iv: Option<GetProgramInterfaceIv>,
+    get_program_resource_name: Option<GetProgramResourceName>,
+    get_program_resource_iv: Option<GetProgramResourceIv>,
+    shader_binary: Option<ShaderBinary>,
+    specialize_shader: Option<SpecializeShader>,
+    multi_draw_elements_indirect: Option<MultiDrawElementsIndirect>,
+    multi_draw_elements_indirect_count: Option<MultiDrawElementsIndirectCount>,
+    bind_image_texture: Option<BindImageTexture>,
+    texture_sub_image_2d: Option<TextureSubImage2D>,
+    get_texture_image: Option<GetTextureImage>,
+}
+
+unsafe fn load_fn<T: Copy>(ptr: *const c_void) -> Option<T> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(std::mem::transmute_copy::<*const c_void, T>(&ptr))
+    }
+}
+
+impl GlExt {
+    pub(crate) fn load<F: FnMut(&str) -> *const c_void>(mut loader: F) -> Self {
+        unsafe {
+            Self {
+                get_program_iv: load_fn(loader("glGetProgramiv")),
+                get_program_binary: load_fn(loader("glGetProgramBinary")),
+                program_binary: load_fn(loader("glProgramBinary")),
+                program_parameter_i: load_fn(loader("glProgramParameteri")),
+                get_program_interface_iv: load_fn(loader("glGetProgramInterfaceiv")),
+                get_program_resource_name: load_fn(loader("glGetProgramResourceName")),
+                get_program_resource_iv: load_fn(loader("glGetProgramResourceiv")),
+                shader_binary: load_fn(loader("glShaderBinary")),
+                specialize_shader: load_fn(loader("glSpecializeShader")),
+                multi_draw_elements_indirect: load_fn(loader("glMultiDrawElementsIndirect")),
+                multi_draw_elements_indirect_count: load_fn(loader("glMultiDrawElementsIndirectCount")),
+                bind_image_texture: load_fn(loader("glBindImageTexture")),
+                texture_sub_image_2d: load_fn(loader("glTextureSubImage2D")),
+                get_texture_image: load_fn(loader("glGetTextureImage")),
+            }
+        }
+    }
+
+    pub(crate) fn supports_program_binary(&self) -> bool {
+        self.get_program_iv.is_some() && self.get_program_binary.is_some() && self.program_binary.is_some() && self.program_parameter_i.is_some()
+    }
+
+    /// Like `glGetProgramiv`, but for parameters that return more than one value
+    pub(crate) unsafe fn get_program_parameter_i32_slice(&self, program: NativeProgram, pname: u32, out: &mut [i32]) {
+        let f = self.get_program_iv.expect("glGetProgramiv not loaded!");
+        f(program.0.get(), pname, out.as_mut_ptr());
+    }
+
+    pub(crate) unsafe fn program_parameter_i32(&self, program: NativeProgram, pname: u32, value: i32) {
+        let f = self.program_parameter_i.expect("glProgramParameteri not loaded!");
+        f(program.0.get(), pname, value);
+    }
+
+    /// Returns the binary format and the program binary
+    pub(crate) unsafe fn get_program_binary(&self, program: NativeProgram) -> Option<(u32, Vec<u8>)> {
+        let mut len = [0i32];
+        self.get_program_parameter_i32_slice(program, PROGRAM_BINARY_LENGTH, &mut len);
+        if len[0] <= 0 {
+            return None;
+        }
+        let f = self.get_program_binary.expect("glGetProgramBinary not loaded!");
+        let mut binary = vec![0u8; len[0] as usize];
+        let mut written = 0i32;
+        let mut format = 0u32;
+        f(program.0.get(), len[0], &mut written, &mut format, binary.as_mut_ptr() as *mut c_void);
+        binary.truncate(written.max(0) as usize);
+        Some((format, binary))
+    }
+
+    pub(crate) unsafe fn program_binary(&self, program: NativeProgram, format: u32, binary: &[u8]) {
+        let f = self.program_binary.expect("glProgramBinary not loaded!");
+        f(program.0.get(), format, binary.as_ptr() as *const c_void, binary.len() as i32);
+    }
+
+    pub(crate) fn supports_program_interface_query(&self) -> bool {
+        self.get_program_interface_iv.is_some() && self.get_program_resource_name.is_some() && self.get_program_resource_iv.is_some()
+    }
+
+    pub(crate) unsafe fn get_program_interface_i32(&self, program: NativeProgram, interface: u32, pname: u32) -> i32 {
+        let f = self.get_program_interface_iv.expect("glGetProgramInterfaceiv not loaded!");
+        let mut value = 0i32;
+        f(program.0.get(), interface, pname, &mut value);
+        value
+    }
+
+    pub(crate) unsafe fn get_program_resource_name(&self, program: NativeProgram, interface: u32, index: u32) -> String {
+        let len = self.get_program_resource_i32(program, interface, index, &[NAME_LENGTH])[0];
+        let f = self.get_program_resource_name.expect("glGetProgramResourceName not loaded!");
+        let mut name = vec![0u8; len.max(1) as usize];
+        let mut written = 0i32;
+        f(program.0.get(), interface, index, name.len() as i32, &mut written, name.as_mut_ptr());
+        name.truncate(written.max(0) as usize);
+        String::from_utf8_lossy(&name).into_owned()
+    }
+
+    /// glow's version of this asks for the result length with a buffer size of 0, which always gives 0
+    pub(crate) unsafe fn get_program_resource_i32(&self, program: NativeProgram, interface: u32, index: u32, props: &[u32]) -> Vec<i32> {
+        let f = self.get_program_resource_iv.expect("glGetProgramResourceiv not loaded!");
+        let mut values = vec![0i32; props.len()];
+        let mut written = 0i32;
+        f(program.0.get(), interface, index, props.len() as i32, props.as_ptr(), values.len() as i32, &mut written, values.as_mut_ptr());
+        values
+    }
+
+    pub(crate) fn supports_spirv(&self) -> bool {
+        self.shader_binary.is_some() && self.specialize_shader.is_some()
+    }
+
+    pub(crate) unsafe fn shader_binary(&self, shader: NativeShader, format: u32, binary: &[u8]) {
+        let f = self.shader_binary.expect("glShaderBinary not loaded!");
+        let shaders = [shader.0.get()];
+        f(1, shaders.as_ptr(), format, binary.as_ptr() as *const c_void, binary.len() as i32);
+    }
+
+    pub(crate) unsafe fn specialize_shader(&self, shader: NativeShader, entry_point: &str, ids: &[u32], values: &[u32]) {
+        let f = self.specialize_shader.expect("glSpecializeShader not loaded!");
+        let entry_point = std::ffi::CString::new(entry_point).expect("Entry point contains a nul byte!");
+        f(shader.0.get(), entry_point.as_ptr(), ids.len() as u32, ids.as_ptr(), values.as_ptr());
+    }
+
+    /// Reads `draw_count` commands from `offset` in the bound `DRAW_INDIRECT_BUFFER`
+    pub(crate) unsafe fn multi_draw_elements_indirect(&self, mode: u32, element_type: u32, offset: usize, draw_count: i32, stride: i32) {
+        let f = self.multi_draw_elements_indirect.expect("glMultiDrawElementsIndirect not loaded!");
+        f(mode, element_type, offset as *const c_void, draw_count, stride);
+    }
+
+    pub(crate) fn supports_indirect_count(&self) -> bool {
+        self.multi_draw_elements_indirect_count.is_some()
+    }
+
+    /// Like `multi_draw_elements_indirect`, with the draw count read from `count_offset` in the bound `PARAMETER_BUFFER`
+    pub(crate) unsafe fn multi_draw_elements_indirect_count(&self, mode: u32, element_type: u32, offset: usize, count_offset: usize, max_draw_count: i32, stride: i32) {
+        let f = self.multi_draw_elements_indirect_count.expect("glMultiDrawElementsIndirectCount not loaded!");
+        f(mode, element_type, offset as *const c_void, count_offset as isize, max_draw_count, stride);
+    }
+
+    /// glow's `bind_image_texture` can't bind texture 0, which is how an image unit gets cleared
+    pub(crate) unsafe fn unbind_image_texture(&self, unit: u32) {
+        let f = self.bind_image_texture.expect("glBindImageTexture not loaded!");
+        f(unit, 0, 0, 0, 0, READ_ONLY, RGBA8);
+    }
+
+    /// Uploads into a texture without binding it, so texture units are left alone
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn texture_sub_image_2d(&self, texture: NativeTexture, level: i32, x_offset: i32, y_offset: i32, width: i32, height: i32, format: u32, ty: u32, pixels: &[u8]) {
+        let f = self.texture_sub_image_2d.expect("glTextureSubImage2D not loaded!");
+        f(texture.0.get(), level, x_offset, y_offset, width, height, format, ty, pixels.as_ptr() as *const c_void);
+    }
+
+    pub(crate) unsafe fn get_texture_image(&self, texture: NativeTexture, level: i32, format: u32, ty: u32, pixels: &mut [u8]) {
+        let f = self.get_texture_image.expect("glGetTextureImage not loaded!");
+        f(texture.0.get(), level, format, ty, pixels.len() as i32, pixels.as_mut_ptr() as *mut c_void);
+    }
+}
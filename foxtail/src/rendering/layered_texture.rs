@@ -0,0 +1,404 @@
+//! Textures with more than one layer: `Texture3D` for volumes, `Texture2DArray` for stacks of same sized images
+//! and `Cubemap`. They take the same `TextureSettings` as `Texture`, with the depth or layer count given separately.
+//! Regions are `[x, y, z]`, where `z` is the depth, the array layer or the cubemap face.
+use glow::*;
+
+use super::barrier::MemoryBarrier;
+use super::sampler::{Filter, Sampler, SamplerSettings, WrapMode};
+use super::shader::ComputeShader;
+use super::texture::{ImageTexture, PixelData, PixelType, Texture, TextureFormat, TextureObject, TextureSettings};
+
+const EQUIRECT_CS: &str = include_str!("shaders/equirect_to_cube_cs.glsl");
+
+/// A volume, sampled with `sampler3D` and bound as `image3D`
+pub struct Texture3D {
+    inner: TextureObject,
+}
+
+impl Texture3D {
+    /// `pixels` are in the default `PixelType` of the format, slice after slice
+    pub fn new(renderer: &super::Renderer, settings: TextureSettings, depth: usize, pixels: Option<&[u8]>) -> Self {
+        Self::with_pixel_type(renderer, settings, depth, settings.format.pixel_type(), pixels)
+    }
+
+    pub fn with_pixel_type(renderer: &super::Renderer, settings: TextureSettings, depth: usize, pixel_type: PixelType, pixels: Option<&[u8]>) -> Self {
+        Self {
+            inner: TextureObject::new(renderer, TEXTURE_3D, settings, depth, pixel_type, pixels, "Texture3D"),
+        }
+    }
+
+    /// Replaces the label shown in the resource registry and graphics debuggers
+    pub fn with_label(self, label: &str) -> Self {
+        self.inner.set_label(label);
+        self
+    }
+
+    pub fn size(&self) -> (usize, usize, usize) {
+        (self.inner.settings.width, self.inner.settings.height, self.inner.depth)
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.inner.settings.format
+    }
+
+    pub fn mip_levels(&self) -> usize {
+        self.inner.mip_levels()
+    }
+
+    /// Size of mip level `level`, depth included
+    pub fn level_size(&self, level: usize) -> [usize; 3] {
+        self.inner.level_size(level)
+    }
+
+    /// Recreates the texture, `pixels` are in the `PixelType` it was created with
+    pub fn resize(&mut self, size: (usize, usize, usize), pixels: Option<&[u8]>) {
+        self.inner.resize(size.0, size.1, size.2, pixels);
+    }
+
+    /// Replaces a box of mip level `level`.
+    /// Panics if the box doesn't fit in the level, or `pixels` doesn't cover it!
+    pub fn write_region(&self, offset: [usize; 3], size: [usize; 3], level: usize, pixels: &[u8]) {
+        self.inner.write_region(offset, size, level, pixels);
+    }
+
+    pub fn write_level(&self, level: usize, pixels: &[u8]) {
+        self.inner.write_level(level, pixels);
+    }
+
+    pub fn generate_mipmaps(&self) {
+        self.inner.generate_mipmaps();
+    }
+
+    /// Reads all slices of mip level `level`, see `Texture::read_pixels`
+    pub fn read_pixels<T: PixelData>(&self, level: usize) -> Vec<T> {
+        self.inner.read_pixels(level)
+    }
+
+    /// Binds the whole volume as an image
+    pub fn bind_img(&self, location: u32, write: bool, read: bool) {
+        self.inner.bind_img(location, None, write, read);
+    }
+
+    /// Binds the slice at depth `layer`, as an `image2D`
+    pub fn bind_img_layer(&self, location: u32, layer: usize, write: bool, read: bool) {
+        self.inner.bind_img(location, Some(layer), write, read);
+    }
+
+    pub fn unbind_img(&self, location: u32) {
+        self.inner.unbind_img(location);
+    }
+
+    pub fn while_bound<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, f: F) -> Result<(), super::RenderError> {
+        self.inner.while_bound(location, f)
+    }
+
+    pub fn while_bound_with<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, sampler: &Sampler, f: F) -> Result<(), super::RenderError> {
+        sampler.while_bound(location, || self.inner.while_bound(location, f))
+    }
+
+    pub fn while_bound_img<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, write: bool, read: bool, f: F) -> Result<(), super::RenderError> {
+        self.inner.while_bound_img(location, None, write, read, f)
+    }
+
+    pub fn while_bound_img_layer<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, layer: usize, write: bool, read: bool, f: F) -> Result<(), super::RenderError> {
+        self.inner.while_bound_img(location, Some(layer), write, read, f)
+    }
+}
+
+impl ImageTexture for Texture3D {
+    fn bind_img(&self, location: u32, write: bool, read: bool) {
+        Texture3D::bind_img(self, location, write, read)
+    }
+
+    unsafe fn tex(&self) -> NativeTexture {
+        self.inner.tex
+    }
+}
+
+/// Layers of the same size and format, sampled with `sampler2DArray` and bound as `image2DArray`
+pub struct Texture2DArray {
+    inner: TextureObject,
+}
+
+impl Texture2DArray {
+    /// `pixels` are in the default `PixelType` of the format, layer after layer
+    pub fn new(renderer: &super::Renderer, settings: TextureSettings, layers: usize, pixels: Option<&[u8]>) -> Self {
+        Self::with_pixel_type(renderer, settings, layers, settings.format.pixel_type(), pixels)
+    }
+
+    pub fn with_pixel_type(renderer: &super::Renderer, settings: TextureSettings, layers: usize, pixel_type: PixelType, pixels: Option<&[u8]>) -> Self {
+        Self {
+            inner: TextureObject::new(renderer, TEXTURE_2D_ARRAY, settings, layers, pixel_type, pixels, "Texture2DArray"),
+        }
+    }
+
+    /// Replaces the label shown in the resource registry and graphics debuggers
+    pub fn with_label(self, label: &str) -> Self {
+        self.inner.set_label(label);
+        self
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.inner.settings.width, self.inner.settings.height)
+    }
+
+    pub fn layers(&self) -> usize {
+        self.inner.depth
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.inner.settings.format
+    }
+
+    pub fn mip_levels(&self) -> usize {
+        self.inner.mip_levels()
+    }
+
+    pub fn level_size(&self, level: usize) -> (usize, usize) {
+        let [width, height, _] = self.inner.level_size(level);
+        (width, height)
+    }
+
+    /// Recreates the texture, `pixels` are in the `PixelType` it was created with
+    pub fn resize(&mut self, size: (usize, usize), layers: usize, pixels: Option<&[u8]>) {
+        self.inner.resize(size.0, size.1, layers, pixels);
+    }
+
+    /// Replaces a rectangle in `size[2]` layers starting at layer `offset[2]`.
+    /// Panics if the region doesn't fit in the level, or `pixels` doesn't cover it!
+    pub fn write_region(&self, offset: [usize; 3], size: [usize; 3], level: usize, pixels: &[u8]) {
+        self.inner.write_region(offset, size, level, pixels);
+    }
+
+    /// Replaces mip level `level` of a single layer
+    pub fn write_layer(&self, layer: usize, level: usize, pixels: &[u8]) {
+        let (width, height) = self.level_size(level);
+        self.inner.write_region([0, 0, layer], [width, height, 1], level, pixels);
+    }
+
+    pub fn generate_mipmaps(&self) {
+        self.inner.generate_mipmaps();
+    }
+
+    /// Reads all layers of mip level `level`, see `Texture::read_pixels`
+    pub fn read_pixels<T: PixelData>(&self, level: usize) -> Vec<T> {
+        self.inner.read_pixels(level)
+    }
+
+    /// Binds every layer as an image
+    pub fn bind_img(&self, location: u32, write: bool, read: bool) {
+        self.inner.bind_img(location, None, write, read);
+    }
+
+    /// Binds a single layer, as an `image2D`
+    pub fn bind_img_layer(&self, location: u32, layer: usize, write: bool, read: bool) {
+        self.inner.bind_img(location, Some(layer), write, read);
+    }
+
+    pub fn unbind_img(&self, location: u32) {
+        self.inner.unbind_img(location);
+    }
+
+    pub fn while_bound<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, f: F) -> Result<(), super::RenderError> {
+        self.inner.while_bound(location, f)
+    }
+
+    pub fn while_bound_with<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, sampler: &Sampler, f: F) -> Result<(), super::RenderError> {
+        sampler.while_bound(location, || self.inner.while_bound(location, f))
+    }
+
+    pub fn while_bound_img<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, write: bool, read: bool, f: F) -> Result<(), super::RenderError> {
+        self.inner.while_bound_img(location, None, write, read, f)
+    }
+
+    pub fn while_bound_img_layer<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, layer: usize, write: bool, read: bool, f: F) -> Result<(), super::RenderError> {
+        self.inner.while_bound_img(location, Some(layer), write, read, f)
+    }
+}
+
+impl ImageTexture for Texture2DArray {
+    fn bind_img(&self, location: u32, write: bool, read: bool) {
+        Texture2DArray::bind_img(self, location, write, read)
+    }
+
+    unsafe fn tex(&self) -> NativeTexture {
+        self.inner.tex
+    }
+}
+
+/// In GL's order, which is also the layer index of each face
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    pub const ALL: [Self; 6] = [Self::PositiveX, Self::NegativeX, Self::PositiveY, Self::NegativeY, Self::PositiveZ, Self::NegativeZ];
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Six square faces, sampled with `samplerCube` and bound as `imageCube`
+pub struct Cubemap {
+    inner: TextureObject,
+}
+
+impl Cubemap {
+    /// `pixels` are in the default `PixelType` of the format, the faces one after another in `CubeFace` order.
+    /// Panics if `settings` isn't square!
+    pub fn new(renderer: &super::Renderer, settings: TextureSettings, pixels: Option<&[u8]>) -> Self {
+        Self::with_pixel_type(renderer, settings, settings.format.pixel_type(), pixels)
+    }
+
+    pub fn with_pixel_type(renderer: &super::Renderer, settings: TextureSettings, pixel_type: PixelType, pixels: Option<&[u8]>) -> Self {
+        if settings.width != settings.height {
+            panic!("Cubemap faces must be square, got {}x{}!", settings.width, settings.height);
+        }
+        // Filter across face edges, instead of sampling each face on its own. This is global state, but never worth turning off
+        unsafe {
+            renderer.gl.enable(TEXTURE_CUBE_MAP_SEAMLESS);
+        }
+        Self {
+            inner: TextureObject::new(renderer, TEXTURE_CUBE_MAP, settings, 6, pixel_type, pixels, "Cubemap"),
+        }
+    }
+
+    /// Like `new`, with every face in its own slice
+    pub fn from_faces(renderer: &super::Renderer, settings: TextureSettings, faces: [&[u8]; 6]) -> Self {
+        Self::new(renderer, settings, Some(&faces.concat()))
+    }
+
+    /// Projects an equirectangular (latitude/longitude) panorama, like most HDR environment maps, onto the faces.
+    /// Runs on the GPU. The top of the panorama is at v = 1, as `Texture::from_path` loads it by default.
+    /// Panics if the cubemap format can't be written by a compute shader, which rules out sRGB and 3 channel formats!
+    pub fn from_equirectangular(renderer: &super::Renderer, equirect: &Texture, settings: TextureSettings) -> Self {
+        let qualifier = match settings.format {
+            TextureFormat::RGBA8 => "rgba8",
+            TextureFormat::RGBA16F => "rgba16f",
+            TextureFormat::RGBA32F => "rgba32f",
+            TextureFormat::R11G11B10F => "r11f_g11f_b10f",
+            other => panic!("Cannot convert an equirectangular image into a {:?} cubemap, use RGBA16F or RGBA32F!", other),
+        };
+        let cubemap = Self::new(renderer, settings, None).with_label("Cubemap from equirectangular");
+        let src = EQUIRECT_CS.replace("FORMAT", qualifier);
+        let shader = ComputeShader::new(renderer, (&src, "foxtail_equirect_to_cube_cs.glsl"));
+        let sampler = Sampler::new(renderer, SamplerSettings {
+            wrap: [WrapMode::Repeat, WrapMode::ClampToEdge, WrapMode::ClampToEdge],
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mipmap_filter: None,
+            ..Default::default()
+        });
+        let size = settings.width as u32;
+        shader.while_bound(|_| {
+            equirect.while_bound_with(0, &sampler, || {
+                cubemap.while_bound_img(0, true, false, || {
+                    shader.dispatch_for_size([size, size, 6]);
+                    Ok(())
+                })
+            })
+        }).expect("Failed to convert equirectangular image!");
+        // Mip generation and sampling come right after, so don't leave it to hazard tracking
+        renderer.barrier(MemoryBarrier::TEXTURE_UPDATE | MemoryBarrier::TEXTURE_FETCH);
+        if settings.mipmap {
+            cubemap.generate_mipmaps();
+        }
+        cubemap
+    }
+
+    /// Replaces the label shown in the resource registry and graphics debuggers
+    pub fn with_label(self, label: &str) -> Self {
+        self.inner.set_label(label);
+        self
+    }
+
+    /// Width and height of a face
+    pub fn size(&self) -> usize {
+        self.inner.settings.width
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.inner.settings.format
+    }
+
+    pub fn mip_levels(&self) -> usize {
+        self.inner.mip_levels()
+    }
+
+    pub fn level_size(&self, level: usize) -> usize {
+        self.inner.level_size(level)[0]
+    }
+
+    /// Recreates the texture, `pixels` are in the `PixelType` it was created with
+    pub fn resize(&mut self, size: usize, pixels: Option<&[u8]>) {
+        self.inner.resize(size, size, 6, pixels);
+    }
+
+    /// Replaces a rectangle of one face.
+    /// Panics if the rectangle doesn't fit in the level, or `pixels` doesn't cover it!
+    pub fn write_region(&self, face: CubeFace, offset: [usize; 2], size: [usize; 2], level: usize, pixels: &[u8]) {
+        self.inner.write_region([offset[0], offset[1], face.index()], [size[0], size[1], 1], level, pixels);
+    }
+
+    /// Replaces mip level `level` of one face
+    pub fn write_face(&self, face: CubeFace, level: usize, pixels: &[u8]) {
+        let size = self.level_size(level);
+        self.write_region(face, [0, 0], [size, size], level, pixels);
+    }
+
+    pub fn generate_mipmaps(&self) {
+        self.inner.generate_mipmaps();
+    }
+
+    /// Reads all faces of mip level `level` in `CubeFace` order, see `Texture::read_pixels`
+    pub fn read_pixels<T: PixelData>(&self, level: usize) -> Vec<T> {
+        self.inner.read_pixels(level)
+    }
+
+    /// Binds every face as an image
+    pub fn bind_img(&self, location: u32, write: bool, read: bool) {
+        self.inner.bind_img(location, None, write, read);
+    }
+
+    /// Binds a single face, as an `image2D`
+    pub fn bind_img_face(&self, location: u32, face: CubeFace, write: bool, read: bool) {
+        self.inner.bind_img(location, Some(face.index()), write, read);
+    }
+
+    pub fn unbind_img(&self, location: u32) {
+        self.inner.unbind_img(location);
+    }
+
+    pub fn while_bound<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, f: F) -> Result<(), super::RenderError> {
+        self.inner.while_bound(location, f)
+    }
+
+    pub fn while_bound_with<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, sampler: &Sampler, f: F) -> Result<(), super::RenderError> {
+        sampler.while_bound(location, || self.inner.while_bound(location, f))
+    }
+
+    pub fn while_bound_img<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, write: bool, read: bool, f: F) -> Result<(), super::RenderError> {
+        self.inner.while_bound_img(location, None, write, read, f)
+    }
+
+    pub fn while_bound_img_face<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, face: CubeFace, write: bool, read: bool, f: F) -> Result<(), super::RenderError> {
+        self.inner.while_bound_img(location, Some(face.index()), write, read, f)
+    }
+}
+
+impl ImageTexture for Cubemap {
+    fn bind_img(&self, location: u32, write: bool, read: bool) {
+        Cubemap::bind_img(self, location, write, read)
+    }
+
+    unsafe fn tex(&self) -> NativeTexture {
+        self.inner.tex
+    }
+}
@@ -1,45 +1,341 @@
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
 use glow::*;
 
+use super::barrier::HazardTracker;
+use super::binding::BindingStack;
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::vertex::{slice_as_bytes, DefaultVertex, Vertex, VertexLayout, VertexStream};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    Points,
+    Lines,
+    LineStrip,
+    LineLoop,
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+    /// For shaders with tessellation stages, the patch size comes from `ShaderBuilder::patch_vertices`
+    Patches,
+}
+
+impl Topology {
+    pub(crate) fn to_gl(self) -> u32 {
+        match self {
+            Self::Points => POINTS,
+            Self::Lines => LINES,
+            Self::LineStrip => LINE_STRIP,
+            Self::LineLoop => LINE_LOOP,
+            Self::Triangles => TRIANGLES,
+            Self::TriangleStrip => TRIANGLE_STRIP,
+            Self::TriangleFan => TRIANGLE_FAN,
+            Self::Patches => PATCHES,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexType {
+    U8,
+    U16,
+    U32,
+}
+
+impl IndexType {
+    pub(crate) fn to_gl(self) -> u32 {
+        match self {
+            Self::U8 => UNSIGNED_BYTE,
+            Self::U16 => UNSIGNED_SHORT,
+            Self::U32 => UNSIGNED_INT,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::U16 => 2,
+            Self::U32 => 4,
+        }
+    }
+}
+
+/// Index data of any width
+#[derive(Debug, Clone, Copy)]
+pub enum Indices<'a> {
+    None,
+    U8(&'a [u8]),
+    U16(&'a [u16]),
+    U32(&'a [u32]),
+}
+
+impl<'a> Indices<'a> {
+    fn bytes(&self) -> Option<(IndexType, &'a [u8], usize)> {
+        match self {
+            Self::None => None,
+            Self::U8(i) => Some((IndexType::U8, slice_as_bytes(i), i.len())),
+            Self::U16(i) => Some((IndexType::U16, slice_as_bytes(i), i.len())),
+            Self::U32(i) => Some((IndexType::U32, slice_as_bytes(i), i.len())),
+        }
+    }
+}
+
+/// `u8`, `u16` or `u32`. The maximum value is the primitive restart index.
+pub trait Index: Copy {
+    fn indices(data: &[Self]) -> Indices<'_>;
+}
+
+impl Index for u8 {
+    fn indices(data: &[Self]) -> Indices<'_> {
+        Indices::U8(data)
+    }
+}
+
+impl Index for u16 {
+    fn indices(data: &[Self]) -> Indices<'_> {
+        Indices::U16(data)
+    }
+}
+
+impl Index for u32 {
+    fn indices(data: &[Self]) -> Indices<'_> {
+        Indices::U32(data)
+    }
+}
+
+/// How often the contents of a mesh change, a hint for the driver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshUsage {
+    /// Uploaded once
+    Static,
+    /// Updated every now and then, like remeshed voxel chunks
+    Dynamic,
+    /// Rewritten every frame, like debug lines. Full updates orphan the old storage instead of waiting on it, `_at` updates don't.
+    Stream,
+}
+
+impl MeshUsage {
+    fn to_gl(self) -> u32 {
+        match self {
+            Self::Static => STATIC_DRAW,
+            Self::Dynamic => DYNAMIC_DRAW,
+            Self::Stream => STREAM_DRAW,
+        }
+    }
+}
+
+/// Replaces the contents of the buffer bound to `target`, which holds `capacity` bytes.
+/// Grows the buffer if needed, or orphans it for streaming meshes. Returns the new capacity.
+unsafe fn upload(gl: &Context, target: u32, capacity: usize, data: &[u8], usage: MeshUsage) -> usize {
+    let capacity = if data.len() > capacity {
+        // Grow by at least half, so meshes growing a bit every update don't reallocate every time
+        let new_capacity = data.len().max(capacity + capacity / 2);
+        gl.buffer_data_size(target, new_capacity as i32, usage.to_gl());
+        new_capacity
+    } else {
+        if usage == MeshUsage::Stream {
+            gl.buffer_data_size(target, capacity as i32, usage.to_gl());
+        }
+        capacity
+    };
+    if !data.is_empty() {
+        gl.buffer_sub_data_u8_slice(target, 0, data);
+    }
+    capacity
+}
+
+/// Overwrites `data` at `offset` in the buffer bound to `target`, which holds `capacity` bytes.
+/// Never orphans, the rest of the buffer has to survive the write.
+unsafe fn upload_at(gl: &Context, target: u32, capacity: usize, offset: usize, data: &[u8]) {
+    if offset + data.len() > capacity {
+        panic!("Cannot write {} bytes at offset {} into a buffer of {} bytes!", data.len(), offset, capacity);
+    }
+    if !data.is_empty() {
+        gl.buffer_sub_data_u8_slice(target, offset as i32, data);
+    }
+}
+
+/// Part of a mesh, for drawing submeshes out of a shared buffer.
+/// `first` and `count` are in indices for indexed meshes, in vertices otherwise.
+/// `base_vertex` gets added to every index (or to `first` for non-indexed meshes).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MeshRange {
+    pub first: u32,
+    pub count: u32,
+    pub base_vertex: i32,
+}
+
 pub struct Mesh {
-    vbo: NativeBuffer,
+    vbos: Vec<NativeBuffer>,
+    /// Size of each vertex buffer in bytes
+    vbo_capacity: Vec<usize>,
+    /// Bytes actually in use in each vertex buffer
+    vbo_len: Vec<usize>,
     vao: NativeVertexArray,
-    ebo: NativeBuffer,
+    ebo: Option<(NativeBuffer, IndexType)>,
+    ebo_capacity: usize,
+    layout: VertexLayout,
+    usage: MeshUsage,
     vert_count: i32,
     index_count: i32,
+    topology: Topology,
+    primitive_restart: bool,
     gl: Arc<Context>,
-    shader_bound: Arc<AtomicBool>,
+    tracker: Arc<HazardTracker>,
+    bindings: Arc<BindingStack>,
+    resources: Arc<ResourceRegistry>,
+    label: String,
 }
 
 impl super::Drawable for Mesh {
     /// Panics if no shader is bound!
     fn draw(&self) -> Result<(), super::RenderError> {
         puffin::profile_function!();
-        if self.shader_bound.load(Ordering::Acquire) != true {
-            panic!("No shader bound! Use `shader.while_bound` or similar!");
-        }
-        unsafe {
-            // self.gl.bind_buffer(ARRAY_BUFFER, Some(self.vbo));
-            self.gl.bind_vertex_array(Some(self.vao));
-            // self.gl.draw_arrays(TRIANGLES, 0, self.vert_count);
-            self.gl.draw_elements(TRIANGLES, self.index_count, UNSIGNED_INT, 0);
-            self.gl.bind_vertex_array(None);
-        }
-        Ok(())
+        self.draw_mode(self.topology, self.full_range(), None)
     }
 }
 
 impl Drop for Mesh {
     fn drop(&mut self) {
+        self.resources.unregister(ResourceKind::VertexArray, self.vao.0.get());
         unsafe {
             self.gl.delete_vertex_array(self.vao);
-            self.gl.delete_buffer(self.vbo);
+            for vbo in &self.vbos {
+                self.resources.unregister(ResourceKind::Buffer, vbo.0.get());
+                self.gl.delete_buffer(*vbo);
+            }
+            if let Some((ebo, _)) = self.ebo {
+                self.resources.unregister(ResourceKind::Buffer, ebo.0.get());
+                self.gl.delete_buffer(ebo);
+            }
         }
     }
 }
 
 impl Mesh {
+    /// `instances` is the instance count and base instance.
+    /// Panics if no shader is bound, or the range is out of bounds!
+    fn draw_mode(&self, topology: Topology, range: MeshRange, instances: Option<(u32, u32)>) -> Result<(), super::RenderError> {
+        if !self.bindings.program_bound() {
+            panic!("No shader bound! Use `shader.while_bound` or similar!");
+        }
+        let len = if self.ebo.is_some() { self.index_count } else { self.vert_count };
+        if range.first as i64 + range.count as i64 > len as i64 {
+            panic!("Cannot draw {:?}, the mesh only has {} elements!", range, len);
+        }
+        if let Some((count, base_instance)) = instances {
+            self.check_instances(count, base_instance);
+        }
+        self.tracker.issuing_commands();
+        unsafe {
+            self.gl.bind_vertex_array(Some(self.vao));
+            if self.primitive_restart {
+                self.gl.enable(PRIMITIVE_RESTART_FIXED_INDEX);
+            }
+            let mode = topology.to_gl();
+            match (self.ebo, instances) {
+                (Some((_, index_type)), None) => {
+                    let offset = (range.first as usize * index_type.size()) as i32;
+                    self.gl.draw_elements_base_vertex(mode, range.count as i32, index_type.to_gl(), offset, range.base_vertex);
+                },
+                (Some((_, index_type)), Some((count, base_instance))) => {
+                    let offset = (range.first as usize * index_type.size()) as i32;
+                    self.gl.draw_elements_instanced_base_vertex_base_instance(mode, range.count as i32, index_type.to_gl(), offset, count as i32, range.base_vertex, base_instance);
+                },
+                (None, None) => self.gl.draw_arrays(mode, range.first as i32 + range.base_vertex, range.count as i32),
+                (None, Some((count, base_instance))) => self.gl.draw_arrays_instanced_base_instance(mode, range.first as i32 + range.base_vertex, range.count as i32, count as i32, base_instance),
+            }
+            if self.primitive_restart {
+                self.gl.disable(PRIMITIVE_RESTART_FIXED_INDEX);
+            }
+            self.gl.bind_vertex_array(None);
+        }
+        self.tracker.commands_issued();
+        Ok(())
+    }
+
+    /// Panics if a per-instance stream doesn't hold enough instances
+    fn check_instances(&self, count: u32, base_instance: u32) {
+        for (stream, len) in self.layout.streams.iter().zip(&self.vbo_len) {
+            if stream.is_per_instance() {
+                let available = (len / stream.stride) as u64 * stream.divisor as u64;
+                if base_instance as u64 + count as u64 > available {
+                    panic!("Cannot draw {} instances from {}, a per-instance stream only holds data for {}!", count, base_instance, available);
+                }
+            }
+        }
+    }
+
+    fn full_range(&self) -> MeshRange {
+        MeshRange {
+            first: 0,
+            count: if self.ebo.is_some() { self.index_count } else { self.vert_count } as u32,
+            base_vertex: 0,
+        }
+    }
+
+    /// Draws part of the mesh with its topology.
+    /// Panics if no shader is bound, or the range is out of bounds!
+    pub fn draw_range(&self, range: MeshRange) -> Result<(), super::RenderError> {
+        puffin::profile_function!();
+        self.draw_mode(self.topology, range, None)
+    }
+
+    /// Draws `count` instances of the mesh in one call.
+    /// Per-instance data comes from streams with a divisor (see `add_instance_stream`),
+    /// or from a `FixedSizeBuffer` bound with `UniformInterface::bind_storage` and indexed by `gl_InstanceID`.
+    /// Panics if no shader is bound!
+    pub fn draw_instanced(&self, count: u32) -> Result<(), super::RenderError> {
+        puffin::profile_function!();
+        self.draw_mode(self.topology, self.full_range(), Some((count, 0)))
+    }
+
+    /// Like `draw_instanced` for part of the mesh. `base_instance` offsets where per-instance streams start reading,
+    /// `gl_InstanceID` still starts at 0.
+    pub fn draw_range_instanced(&self, range: MeshRange, count: u32, base_instance: u32) -> Result<(), super::RenderError> {
+        puffin::profile_function!();
+        self.draw_mode(self.topology, range, Some((count, base_instance)))
+    }
+
+    /// Draws the mesh as `PATCHES` for shaders with tessellation stages.
+    /// The patch size comes from `ShaderBuilder::patch_vertices`.
+    /// Panics if no shader is bound!
+    pub fn draw_patches(&self) -> Result<(), super::RenderError> {
+        puffin::profile_function!();
+        self.draw_mode(Topology::Patches, self.full_range(), None)
+    }
+
+    /// Defaults to `Triangles`
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Restarts strips and fans at the maximum value of the index type (`0xFF`, `0xFFFF` or `0xFFFFFFFF`)
+    pub fn with_primitive_restart(mut self, enabled: bool) -> Self {
+        if enabled && self.ebo.is_none() {
+            warn!("Primitive restart enabled on a mesh without indices!");
+        }
+        self.primitive_restart = enabled;
+        self
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vert_count as usize
+    }
+
+    /// `None` for non-indexed meshes
+    pub fn index_count(&self) -> Option<usize> {
+        self.ebo.map(|_| self.index_count as usize)
+    }
+
     pub fn quad(renderer: &super::Renderer) -> Self {
         let quad_vertices: [f32; 32] = [
             // Position    // Color     // UV
@@ -57,54 +353,259 @@ impl Mesh {
         Self::from_verts_indices(renderer, &quad_vertices, &quad_indices)
     }
 
-    pub fn from_vertices(renderer: &super::Renderer, vertex_data: &[f32]) -> Self {
-        todo!()
+    /// Non-indexed mesh, drawn with `draw_arrays`
+    pub fn from_vertices<V: Vertex>(renderer: &super::Renderer, vertices: &[V]) -> Self {
+        Self::from_layout(renderer, &VertexLayout::interleaved(V::layout()), &[slice_as_bytes(vertices)], Indices::None)
+    }
+
+    pub fn from_vertices_indices<V: Vertex, I: Index>(renderer: &super::Renderer, vertices: &[V], indices: &[I]) -> Self {
+        Self::from_layout(renderer, &VertexLayout::interleaved(V::layout()), &[slice_as_bytes(vertices)], I::indices(indices))
     }
 
+    /// Interleaved `DefaultVertex` data, 8 floats per vertex
     pub fn from_verts_indices(renderer: &super::Renderer, vertex_data: &[f32], index_data: &[u32]) -> Self {
-        unsafe {
-            let vertices_u8: &[u8] = core::slice::from_raw_parts(
-                vertex_data.as_ptr() as *const u8,
-                vertex_data.len() * core::mem::size_of::<f32>(),
-            );
+        if !vertex_data.len().is_multiple_of(8) {
+            panic!("Vertex data has to be 8 floats per vertex (position, color, uv)!");
+        }
+        Self::from_layout(renderer, &VertexLayout::interleaved(DefaultVertex::layout()), &[slice_as_bytes(vertex_data)], Indices::U32(index_data))
+    }
+
+    /// Builds a mesh from raw bytes, one slice per stream in `layout`.
+    /// Panics if the streams don't all hold the same number of vertices!
+    pub fn from_layout(renderer: &super::Renderer, layout: &VertexLayout, streams: &[&[u8]], indices: Indices) -> Self {
+        Self::from_layout_with_usage(renderer, layout, streams, indices, MeshUsage::Static)
+    }
 
-            let indices_u8: &[u8] = core::slice::from_raw_parts(
-                index_data.as_ptr() as *const u8,
-                index_data.len() * core::mem::size_of::<u32>(),
-            );
+    /// A mesh without any vertices yet, to be filled with `update_vertices`
+    pub fn empty(renderer: &super::Renderer, layout: &VertexLayout, usage: MeshUsage) -> Self {
+        let streams = vec![&[][..]; layout.streams.len()];
+        Self::from_layout_with_usage(renderer, layout, &streams, Indices::None, usage)
+    }
+
+    /// An empty mesh for geometry that gets rewritten every frame
+    pub fn streaming<V: Vertex>(renderer: &super::Renderer) -> Self {
+        Self::empty(renderer, &VertexLayout::interleaved(V::layout()), MeshUsage::Stream)
+    }
+
+    pub fn from_layout_with_usage(renderer: &super::Renderer, layout: &VertexLayout, streams: &[&[u8]], indices: Indices, usage: MeshUsage) -> Self {
+        if streams.len() != layout.streams.len() {
+            panic!("Vertex layout has {} streams, but {} were given!", layout.streams.len(), streams.len());
+        }
+        let mut vert_count = None;
+        for (stream, data) in layout.streams.iter().zip(streams) {
+            if stream.stride == 0 || data.len() % stream.stride != 0 {
+                panic!("Vertex stream of {} bytes is not a multiple of its stride ({} bytes)!", data.len(), stream.stride);
+            }
+            if stream.is_per_instance() {
+                continue;
+            }
+            let count = data.len() / stream.stride;
+            if *vert_count.get_or_insert(count) != count {
+                panic!("Vertex streams hold different numbers of vertices!");
+            }
+        }
 
+        unsafe {
             let gl = renderer.gl.clone();
-            trace!("GL cloned!");
 
             let vao = gl.create_vertex_array().expect("Failed to create VAO!");
             gl.bind_vertex_array(Some(vao));
+            renderer.resources.register(ResourceKind::VertexArray, vao.0.get(), 0, "Mesh");
 
-            let vbo = gl.create_buffer().expect("Failed to create VBO!");
-            gl.bind_buffer(ARRAY_BUFFER, Some(vbo));
-            gl.buffer_data_u8_slice(ARRAY_BUFFER, vertices_u8, STATIC_DRAW);
-
-            let ebo = gl.create_buffer().expect("Failed to create EBO!");
-            gl.bind_buffer(ELEMENT_ARRAY_BUFFER, Some(ebo));
-            gl.buffer_data_u8_slice(ELEMENT_ARRAY_BUFFER, indices_u8, STATIC_DRAW);
+            let mut vbos = Vec::new();
+            for (i, (stream, data)) in layout.streams.iter().zip(streams).enumerate() {
+                let vbo = gl.create_buffer().expect("Failed to create VBO!");
+                gl.bind_buffer(ARRAY_BUFFER, Some(vbo));
+                gl.buffer_data_u8_slice(ARRAY_BUFFER, data, usage.to_gl());
+                stream.apply(&gl);
+                renderer.resources.register(ResourceKind::Buffer, vbo.0.get(), data.len(), &format!("Mesh stream {}", i));
+                vbos.push(vbo);
+            }
 
-            gl.enable_vertex_attrib_array(0);
-            gl.vertex_attrib_pointer_f32(0, 3, FLOAT, false, (8 * core::mem::size_of::<f32>()) as i32, 0);
-            gl.enable_vertex_attrib_array(1);
-            gl.vertex_attrib_pointer_f32(1, 3, FLOAT, false, (8 * core::mem::size_of::<f32>()) as i32, (3 * core::mem::size_of::<f32>()) as i32);
-            gl.enable_vertex_attrib_array(2);
-            gl.vertex_attrib_pointer_f32(2, 2, FLOAT, false, (8 * core::mem::size_of::<f32>()) as i32, (6 * core::mem::size_of::<f32>()) as i32);
+            let mut index_count = 0;
+            let ebo_capacity = indices.bytes().map_or(0, |(_, data, _)| data.len());
+            let ebo = indices.bytes().map(|(index_type, data, count)| {
+                let ebo = gl.create_buffer().expect("Failed to create EBO!");
+                gl.bind_buffer(ELEMENT_ARRAY_BUFFER, Some(ebo));
+                gl.buffer_data_u8_slice(ELEMENT_ARRAY_BUFFER, data, usage.to_gl());
+                renderer.resources.register(ResourceKind::Buffer, ebo.0.get(), data.len(), "Mesh indices");
+                index_count = count;
+                (ebo, index_type)
+            });
 
             gl.bind_vertex_array(None);
+            gl.bind_buffer(ARRAY_BUFFER, None);
 
             Self {
-                vbo,
+                vbos,
+                vbo_capacity: streams.iter().map(|data| data.len()).collect(),
+                vbo_len: streams.iter().map(|data| data.len()).collect(),
                 vao,
                 ebo,
-                vert_count: (vertex_data.len() / 8) as i32,
-                index_count: index_data.len() as i32,
+                ebo_capacity,
+                layout: layout.clone(),
+                usage,
+                vert_count: vert_count.unwrap_or(0) as i32,
+                index_count: index_count as i32,
+                topology: Topology::Triangles,
+                primitive_restart: false,
                 gl,
-                shader_bound: renderer.shader_bound.clone(),
+                tracker: renderer.tracker.clone(),
+                bindings: renderer.bindings.clone(),
+                resources: renderer.resources.clone(),
+                label: "Mesh".to_string(),
             }
         }
     }
+
+    /// Replaces the label shown in the resource registry and graphics debuggers.
+    /// Buffers get `"<label> stream <i>"` and `"<label> indices"`.
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = label.to_string();
+        self.resources.set_label(ResourceKind::VertexArray, self.vao.0.get(), label);
+        for (i, vbo) in self.vbos.iter().enumerate() {
+            self.resources.set_label(ResourceKind::Buffer, vbo.0.get(), &format!("{} stream {}", label, i));
+        }
+        if let Some((ebo, _)) = self.ebo {
+            self.resources.set_label(ResourceKind::Buffer, ebo.0.get(), &format!("{} indices", label));
+        }
+        self
+    }
+
+    /// Replaces all vertices of an interleaved mesh.
+    /// Panics if `V` doesn't match the layout the mesh was created with!
+    pub fn update_vertices<V: Vertex>(&mut self, vertices: &[V]) {
+        self.check_vertex_type::<V>();
+        self.update_stream(0, slice_as_bytes(vertices));
+    }
+
+    /// Overwrites vertices in place, starting at vertex `first`.
+    /// Partial writes never orphan the buffer, not even on `MeshUsage::Stream` meshes.
+    /// Panics if that goes past the current vertex count!
+    pub fn update_vertices_at<V: Vertex>(&mut self, first: usize, vertices: &[V]) {
+        self.check_vertex_type::<V>();
+        self.update_stream_at(0, first, slice_as_bytes(vertices));
+    }
+
+    fn check_vertex_type<V: Vertex>(&self) {
+        let vertex_streams = self.layout.streams.iter().filter(|s| !s.is_per_instance()).count();
+        if vertex_streams != 1 || self.layout.streams[0] != V::layout() {
+            panic!("Vertex type doesn't match the layout of the mesh!");
+        }
+    }
+
+    /// Adds a per-instance stream (a `VertexStream` with a divisor) to the mesh.
+    /// Returns its index, to fill it with `update_instances` or `update_stream`.
+    pub fn add_instance_stream(&mut self, stream: VertexStream) -> usize {
+        if !stream.is_per_instance() {
+            panic!("Instance streams need a divisor, see `VertexStream::per_instance`!");
+        }
+        unsafe {
+            let vbo = self.gl.create_buffer().expect("Failed to create VBO!");
+            self.gl.bind_vertex_array(Some(self.vao));
+            self.gl.bind_buffer(ARRAY_BUFFER, Some(vbo));
+            self.resources.register(ResourceKind::Buffer, vbo.0.get(), 0, &format!("{} stream {}", self.label, self.vbos.len()));
+            stream.apply(&self.gl);
+            self.gl.bind_vertex_array(None);
+            self.gl.bind_buffer(ARRAY_BUFFER, None);
+            self.vbos.push(vbo);
+        }
+        self.vbo_capacity.push(0);
+        self.vbo_len.push(0);
+        self.layout.streams.push(stream);
+        self.layout.streams.len() - 1
+    }
+
+    /// Replaces the data of a per-instance stream, typically every frame.
+    /// Panics if `I` doesn't have the stride of the stream!
+    pub fn update_instances<I: Copy>(&mut self, stream: usize, instances: &[I]) {
+        if !self.layout.streams[stream].is_per_instance() || self.layout.streams[stream].stride != std::mem::size_of::<I>() {
+            panic!("Stream {} is not a per-instance stream with a stride of {} bytes!", stream, std::mem::size_of::<I>());
+        }
+        self.update_stream(stream, slice_as_bytes(instances));
+    }
+
+    /// Replaces the contents of one stream, for meshes with separate streams.
+    /// For per-vertex streams, the vertex count becomes that of the last updated stream, keep them equal before drawing.
+    pub fn update_stream(&mut self, stream: usize, data: &[u8]) {
+        let stride = self.layout.streams[stream].stride;
+        if !data.len().is_multiple_of(stride) {
+            panic!("Vertex stream of {} bytes is not a multiple of its stride ({} bytes)!", data.len(), stride);
+        }
+        unsafe {
+            self.gl.bind_buffer(ARRAY_BUFFER, Some(self.vbos[stream]));
+            self.vbo_capacity[stream] = upload(&self.gl, ARRAY_BUFFER, self.vbo_capacity[stream], data, self.usage);
+            self.gl.bind_buffer(ARRAY_BUFFER, None);
+        }
+        self.resources.set_bytes(ResourceKind::Buffer, self.vbos[stream].0.get(), self.vbo_capacity[stream]);
+        self.vbo_len[stream] = data.len();
+        if !self.layout.streams[stream].is_per_instance() {
+            self.vert_count = (data.len() / stride) as i32;
+        }
+    }
+
+    /// Overwrites part of one stream in place, starting at vertex `first`.
+    /// Like `update_vertices_at`, this never orphans the buffer.
+    pub fn update_stream_at(&mut self, stream: usize, first: usize, data: &[u8]) {
+        let stride = self.layout.streams[stream].stride;
+        if !data.len().is_multiple_of(stride) {
+            panic!("Vertex stream of {} bytes is not a multiple of its stride ({} bytes)!", data.len(), stride);
+        }
+        if first * stride + data.len() > self.vbo_len[stream] {
+            panic!("Cannot update vertices past the end of the stream, use `update_stream` to grow it!");
+        }
+        unsafe {
+            self.gl.bind_buffer(ARRAY_BUFFER, Some(self.vbos[stream]));
+            upload_at(&self.gl, ARRAY_BUFFER, self.vbo_capacity[stream], first * stride, data);
+            self.gl.bind_buffer(ARRAY_BUFFER, None);
+        }
+    }
+
+    /// Replaces all indices. The index type may change, and non-indexed meshes become indexed.
+    pub fn update_indices<I: Index>(&mut self, indices: &[I]) {
+        let (index_type, data, count) = I::indices(indices).bytes().unwrap();
+        unsafe {
+            // The element buffer binding belongs to the VAO
+            self.gl.bind_vertex_array(Some(self.vao));
+            match self.ebo {
+                Some((ebo, _)) => self.gl.bind_buffer(ELEMENT_ARRAY_BUFFER, Some(ebo)),
+                None => {
+                    let ebo = self.gl.create_buffer().expect("Failed to create EBO!");
+                    self.gl.bind_buffer(ELEMENT_ARRAY_BUFFER, Some(ebo));
+                    self.resources.register(ResourceKind::Buffer, ebo.0.get(), 0, &format!("{} indices", self.label));
+                    self.ebo = Some((ebo, index_type));
+                },
+            }
+            self.ebo_capacity = upload(&self.gl, ELEMENT_ARRAY_BUFFER, self.ebo_capacity, data, self.usage);
+            self.gl.bind_vertex_array(None);
+        }
+        self.ebo = self.ebo.map(|(ebo, _)| (ebo, index_type));
+        if let Some((ebo, _)) = self.ebo {
+            self.resources.set_bytes(ResourceKind::Buffer, ebo.0.get(), self.ebo_capacity);
+        }
+        self.index_count = count as i32;
+    }
+
+    /// Overwrites indices in place, starting at index `first`. This never orphans the buffer.
+    /// Panics if the index type differs, or that goes past the current index count!
+    pub fn update_indices_at<I: Index>(&mut self, first: usize, indices: &[I]) {
+        let (index_type, data, count) = I::indices(indices).bytes().unwrap();
+        let ebo = match self.ebo {
+            Some((ebo, current)) if current == index_type => ebo,
+            _ => panic!("Cannot update {:?} indices in place on a mesh with {:?} indices!", index_type, self.ebo.map(|(_, t)| t)),
+        };
+        if first + count > self.index_count as usize {
+            panic!("Cannot update indices past the end of the mesh, use `update_indices` to grow it!");
+        }
+        unsafe {
+            self.gl.bind_vertex_array(Some(self.vao));
+            self.gl.bind_buffer(ELEMENT_ARRAY_BUFFER, Some(ebo));
+            upload_at(&self.gl, ELEMENT_ARRAY_BUFFER, self.ebo_capacity, first * index_type.size(), data);
+            self.gl.bind_vertex_array(None);
+        }
+    }
+
+    pub fn usage(&self) -> MeshUsage {
+        self.usage
+    }
 }
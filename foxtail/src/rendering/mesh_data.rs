@@ -0,0 +1,605 @@
+//! CPU side geometry. Everything here works without a GPU, upload with `Mesh::from_data`.
+use std::collections::HashMap;
+
+use super::mesh::{Indices, Mesh};
+use super::vertex::{slice_as_bytes, AttribType, VertexLayout, VertexStream};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Aabb {
+    pub fn center(&self) -> [f32; 3] {
+        scale(add(self.min, self.max), 0.5)
+    }
+
+    pub fn size(&self) -> [f32; 3] {
+        sub(self.max, self.min)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: [f32; 3],
+    pub radius: f32,
+}
+
+/// Indexed triangle list. Every attribute is either empty or has one entry per position.
+///
+/// Uploaded as separate streams, at the same locations `rendering::scene` uses:
+/// positions at 0, normals at 1, uvs at 2, tangents at 3 and colors at 5.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MeshData {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    /// `w` is the handedness, the bitangent is `cross(normal, tangent.xyz) * w`
+    pub tangents: Vec<[f32; 4]>,
+    pub colors: Vec<[f32; 4]>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn triangles(&self) -> impl Iterator<Item = [usize; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+    }
+
+    fn check_attributes(&self) {
+        let count = self.positions.len();
+        for (name, len) in [("normals", self.normals.len()), ("uvs", self.uvs.len()), ("tangents", self.tangents.len()), ("colors", self.colors.len())] {
+            if len != 0 && len != count {
+                panic!("Mesh data has {} positions but {} {}!", count, len, name);
+            }
+        }
+        if !self.indices.len().is_multiple_of(3) {
+            panic!("Mesh data indices have to be a triangle list!");
+        }
+    }
+
+    /// Area weighted vertex normals. Vertices are only smoothed across triangles they share,
+    /// so `weld` first to smooth over seams
+    pub fn compute_smooth_normals(&mut self) {
+        let mut normals = vec![[0.0; 3]; self.positions.len()];
+        for [a, b, c] in self.triangles() {
+            let n = face_normal(self.positions[a], self.positions[b], self.positions[c]);
+            for i in [a, b, c] {
+                normals[i] = add(normals[i], n);
+            }
+        }
+        self.normals = normals.into_iter().map(normalize).collect();
+    }
+
+    /// Gives every triangle its own vertices with the face normal
+    pub fn compute_flat_normals(&mut self) {
+        let corners = std::mem::take(&mut self.indices);
+        self.remap(&corners.iter().map(|i| *i as usize).collect::<Vec<usize>>());
+        self.indices = (0..corners.len() as u32).collect();
+        self.normals = self.positions.chunks_exact(3).flat_map(|t| [normalize(face_normal(t[0], t[1], t[2])); 3]).collect();
+    }
+
+    /// MikkTSpace tangents, the same ones most bakers use. Panics without normals or uvs!
+    pub fn compute_tangents(&mut self) {
+        if self.normals.is_empty() || self.uvs.is_empty() {
+            panic!("Computing tangents needs normals and uvs!");
+        }
+        self.check_attributes();
+        let mut geometry = TangentGeometry { data: self, tangents: vec![[1.0, 0.0, 0.0, 1.0]; self.positions.len()] };
+        if !mikktspace::generate_tangents(&mut geometry) {
+            warn!("Failed to generate tangents, using +X for every vertex!");
+        }
+        self.tangents = geometry.tangents;
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        let mut aabb = Aabb { min: [f32::MAX; 3], max: [f32::MIN; 3] };
+        for p in &self.positions {
+            aabb.min = [0, 1, 2].map(|i| aabb.min[i].min(p[i]));
+            aabb.max = [0, 1, 2].map(|i| aabb.max[i].max(p[i]));
+        }
+        if self.positions.is_empty() {
+            aabb = Aabb { min: [0.0; 3], max: [0.0; 3] };
+        }
+        aabb
+    }
+
+    /// Ritter's bounding sphere, at most ~5% larger than the optimal one
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        let Some(first) = self.positions.first() else {
+            return BoundingSphere { center: [0.0; 3], radius: 0.0 };
+        };
+        let farthest = |from: [f32; 3]| *self.positions.iter().max_by(|a, b| distance_sq(from, **a).total_cmp(&distance_sq(from, **b))).unwrap();
+        let a = farthest(*first);
+        let b = farthest(a);
+        let mut center = scale(add(a, b), 0.5);
+        let mut radius = distance_sq(a, b).sqrt() * 0.5;
+        for p in &self.positions {
+            let d = distance_sq(center, *p).sqrt();
+            if d > radius {
+                let new_radius = (radius + d) * 0.5;
+                center = add(center, scale(sub(*p, center), (new_radius - radius) / d));
+                radius = new_radius;
+            }
+        }
+        BoundingSphere { center, radius }
+    }
+
+    /// Applies a column-major 4x4 matrix. Normals are transformed with the inverse transpose, tangents with the matrix itself,
+    /// and mirroring matrices flip the winding so front faces stay front faces
+    pub fn transform(&mut self, matrix: [f32; 16]) {
+        let m = |v: [f32; 3]| [
+            matrix[0] * v[0] + matrix[4] * v[1] + matrix[8] * v[2],
+            matrix[1] * v[0] + matrix[5] * v[1] + matrix[9] * v[2],
+            matrix[2] * v[0] + matrix[6] * v[1] + matrix[10] * v[2],
+        ];
+        let (c0, c1, c2) = ([matrix[0], matrix[1], matrix[2]], [matrix[4], matrix[5], matrix[6]], [matrix[8], matrix[9], matrix[10]]);
+        // Columns of the cofactor matrix, which is the inverse transpose scaled by the determinant
+        let cofactor = [cross(c1, c2), cross(c2, c0), cross(c0, c1)];
+        let det = dot(c0, cofactor[0]);
+        let sign = if det < 0.0 { -1.0 } else { 1.0 };
+
+        for p in self.positions.iter_mut() {
+            *p = add(m(*p), [matrix[12], matrix[13], matrix[14]]);
+        }
+        for n in self.normals.iter_mut() {
+            *n = normalize(scale(add(add(scale(cofactor[0], n[0]), scale(cofactor[1], n[1])), scale(cofactor[2], n[2])), sign));
+        }
+        for t in self.tangents.iter_mut() {
+            let [x, y, z] = normalize(m([t[0], t[1], t[2]]));
+            *t = [x, y, z, t[3] * sign];
+        }
+        if det < 0.0 {
+            for t in self.indices.chunks_exact_mut(3) {
+                t.swap(1, 2);
+            }
+        }
+    }
+
+    /// Appends another mesh. Both have to have the same attributes, unless `self` is empty
+    pub fn merge(&mut self, other: &MeshData) {
+        if self.positions.is_empty() && self.indices.is_empty() {
+            *self = other.clone();
+            return;
+        }
+        let has = |m: &MeshData| [!m.normals.is_empty(), !m.uvs.is_empty(), !m.tangents.is_empty(), !m.colors.is_empty()];
+        if has(self) != has(other) {
+            panic!("Cannot merge mesh data with different attributes!");
+        }
+        let offset = self.positions.len() as u32;
+        self.positions.extend_from_slice(&other.positions);
+        self.normals.extend_from_slice(&other.normals);
+        self.uvs.extend_from_slice(&other.uvs);
+        self.tangents.extend_from_slice(&other.tangents);
+        self.colors.extend_from_slice(&other.colors);
+        self.indices.extend(other.indices.iter().map(|i| i + offset));
+    }
+
+    /// Merges vertices whose attributes all match after snapping to a grid of `epsilon`.
+    /// `0.0` only merges exact duplicates
+    pub fn weld(&mut self, epsilon: f32) {
+        self.check_attributes();
+        let quantize = |v: f32| if epsilon > 0.0 { (v / epsilon).round() as i64 } else { v.to_bits() as i64 };
+        let mut unique = HashMap::new();
+        let mut kept = Vec::new();
+        let mut remap = Vec::with_capacity(self.positions.len());
+        for i in 0..self.positions.len() {
+            let mut key = Vec::with_capacity(16);
+            key.extend(self.positions[i].iter().map(|v| quantize(*v)));
+            key.extend(self.normals.get(i).iter().flat_map(|v| v.iter()).map(|v| quantize(*v)));
+            key.extend(self.uvs.get(i).iter().flat_map(|v| v.iter()).map(|v| quantize(*v)));
+            key.extend(self.tangents.get(i).iter().flat_map(|v| v.iter()).map(|v| quantize(*v)));
+            key.extend(self.colors.get(i).iter().flat_map(|v| v.iter()).map(|v| quantize(*v)));
+            remap.push(*unique.entry(key).or_insert_with(|| {
+                kept.push(i);
+                kept.len() as u32 - 1
+            }));
+        }
+        self.remap(&kept);
+        for i in self.indices.iter_mut() {
+            *i = remap[*i as usize];
+        }
+    }
+
+    /// Reorders triangles for the post-transform vertex cache (Forsyth's algorithm),
+    /// then vertices in the order they are first used. Unused vertices are dropped
+    pub fn optimize_vertex_cache(&mut self) {
+        const CACHE_SIZE: usize = 32;
+        let vertex_score = |cache_position: Option<usize>, remaining: usize| -> f32 {
+            if remaining == 0 {
+                return -1.0;
+            }
+            let cache_score = match cache_position {
+                None => 0.0,
+                Some(p) if p < 3 => 0.75,
+                Some(p) => (1.0 - (p - 3) as f32 / (CACHE_SIZE - 3) as f32).powf(1.5),
+            };
+            cache_score + 2.0 * (remaining as f32).powf(-0.5)
+        };
+
+        self.check_attributes();
+        let triangles = self.triangles().collect::<Vec<[usize; 3]>>();
+        let mut vertex_triangles = vec![Vec::new(); self.positions.len()];
+        for (t, triangle) in triangles.iter().enumerate() {
+            for v in triangle {
+                vertex_triangles[*v].push(t);
+            }
+        }
+        let mut cache_position = vec![None; self.positions.len()];
+        let mut scores = vertex_triangles.iter().map(|t| vertex_score(None, t.len())).collect::<Vec<f32>>();
+        let mut triangle_scores = triangles.iter().map(|t| t.iter().map(|v| scores[*v]).sum()).collect::<Vec<f32>>();
+        let mut added = vec![false; triangles.len()];
+        let mut cache: Vec<usize> = Vec::new();
+        let mut order = Vec::with_capacity(self.indices.len());
+        let mut best = (0..triangles.len()).max_by(|a, b| triangle_scores[*a].total_cmp(&triangle_scores[*b]));
+        let mut cursor = 0;
+
+        while let Some(triangle) = best {
+            added[triangle] = true;
+            order.extend(triangles[triangle].iter().map(|v| *v as u32));
+            for v in triangles[triangle] {
+                vertex_triangles[v].retain(|t| *t != triangle);
+            }
+
+            let mut new_cache = triangles[triangle].to_vec();
+            new_cache.extend(cache.iter().filter(|v| !triangles[triangle].contains(v)));
+            for (i, v) in new_cache.iter().enumerate() {
+                cache_position[*v] = if i < CACHE_SIZE { Some(i) } else { None };
+                scores[*v] = vertex_score(cache_position[*v], vertex_triangles[*v].len());
+            }
+
+            best = None;
+            let mut best_score = f32::MIN;
+            for v in &new_cache {
+                for t in &vertex_triangles[*v] {
+                    triangle_scores[*t] = triangles[*t].iter().map(|v| scores[*v]).sum();
+                    if triangle_scores[*t] > best_score {
+                        best = Some(*t);
+                        best_score = triangle_scores[*t];
+                    }
+                }
+            }
+            new_cache.truncate(CACHE_SIZE);
+            cache = new_cache;
+
+            if best.is_none() {
+                while cursor < triangles.len() && added[cursor] {
+                    cursor += 1;
+                }
+                best = (cursor < triangles.len()).then_some(cursor);
+            }
+        }
+
+        let mut remap = vec![u32::MAX; self.positions.len()];
+        let mut kept = Vec::new();
+        self.indices = order.into_iter().map(|v| {
+            if remap[v as usize] == u32::MAX {
+                remap[v as usize] = kept.len() as u32;
+                kept.push(v as usize);
+            }
+            remap[v as usize]
+        }).collect();
+        self.remap(&kept);
+    }
+
+    /// Replaces every attribute with the entries at `source`
+    fn remap(&mut self, source: &[usize]) {
+        fn pick<T: Copy>(v: &mut Vec<T>, source: &[usize]) {
+            if !v.is_empty() {
+                *v = source.iter().map(|i| v[*i]).collect();
+            }
+        }
+        pick(&mut self.positions, source);
+        pick(&mut self.normals, source);
+        pick(&mut self.uvs, source);
+        pick(&mut self.tangents, source);
+        pick(&mut self.colors, source);
+    }
+}
+
+struct TangentGeometry<'d> {
+    data: &'d MeshData,
+    tangents: Vec<[f32; 4]>,
+}
+
+impl mikktspace::Geometry for TangentGeometry<'_> {
+    fn num_faces(&self) -> usize {
+        self.data.triangle_count()
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.data.positions[self.data.indices[face * 3 + vert] as usize]
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.data.normals[self.data.indices[face * 3 + vert] as usize]
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.data.uvs[self.data.indices[face * 3 + vert] as usize]
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.tangents[self.data.indices[face * 3 + vert] as usize] = tangent;
+    }
+}
+
+fn face_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    cross(sub(b, a), sub(c, a))
+}
+
+fn distance_sq(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let d = sub(a, b);
+    dot(d, d)
+}
+
+pub(crate) fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub(crate) fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn scale(a: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+pub(crate) fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+pub(crate) fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let len = dot(a, a).sqrt();
+    if len == 0.0 { a } else { scale(a, 1.0 / len) }
+}
+
+impl Mesh {
+    pub fn from_data(renderer: &super::Renderer, data: &MeshData) -> Self {
+        data.check_attributes();
+        let attributes = [
+            (0, 3, slice_as_bytes(&data.positions)),
+            (1, 3, slice_as_bytes(&data.normals)),
+            (2, 2, slice_as_bytes(&data.uvs)),
+            (3, 4, slice_as_bytes(&data.tangents)),
+            (5, 4, slice_as_bytes(&data.colors)),
+        ];
+        let mut streams = Vec::new();
+        let mut bytes = Vec::new();
+        for (location, components, raw) in attributes {
+            if raw.is_empty() {
+                continue;
+            }
+            streams.push(VertexStream::new(components as usize * 4).float(location, AttribType::F32, components, 0));
+            bytes.push(raw);
+        }
+        Self::from_layout(renderer, &VertexLayout::separate(streams), &bytes, Indices::U32(&data.indices))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every triangle as its positions, rotated so the smallest corner comes first to keep the winding
+    fn triangle_set(data: &MeshData) -> Vec<[[i32; 3]; 3]> {
+        let key = |p: [f32; 3]| p.map(|v| (v * 1000.0).round() as i32);
+        let mut set = data.triangles().map(|t| {
+            let mut corners = t.map(|i| key(data.positions[i]));
+            let first = (0..3).min_by_key(|i| corners[*i]).unwrap();
+            corners.rotate_left(first);
+            corners
+        }).collect::<Vec<_>>();
+        set.sort();
+        set
+    }
+
+    fn quad() -> MeshData {
+        MeshData {
+            positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]],
+            indices: vec![0, 1, 2, 3, 4, 5],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn weld_duplicated_quad() {
+        let mut data = quad();
+        data.weld(0.0);
+        assert_eq!(data.vertex_count(), 4);
+        assert_eq!(data.indices, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(triangle_set(&data), triangle_set(&quad()));
+
+        // Vertices only match if every attribute does
+        let mut data = quad();
+        data.uvs = vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.5, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        data.weld(0.0);
+        assert_eq!(data.vertex_count(), 5);
+
+        let mut data = quad();
+        data.positions[3] = [0.0001, 0.0, 0.0];
+        data.weld(0.01);
+        assert_eq!(data.vertex_count(), 4);
+    }
+
+    #[test]
+    fn mirrored_transform_flips_winding() {
+        let mut data = quad();
+        data.compute_flat_normals();
+        let mirror = [
+            -1.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ];
+        data.transform(mirror);
+        assert_eq!(data.indices, [0, 2, 1, 3, 5, 4]);
+        // Still facing +Z, and the winding agrees with the normals
+        assert!(data.normals.iter().all(|n| *n == [0.0, 0.0, 1.0]));
+        let mut flat = data.clone();
+        flat.compute_flat_normals();
+        assert_eq!(flat.normals, data.normals);
+
+        let mut data = quad();
+        data.transform([2.0, 0.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.0, 2.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(data.indices, quad().indices);
+        assert_eq!(data.positions[2], [3.0, 2.0, 0.0]);
+    }
+
+    #[test]
+    fn optimize_vertex_cache_keeps_triangles() {
+        let mut data = MeshData::uv_sphere(1.0, 16, 8);
+        // Shuffle the triangles so there's something to optimize
+        let mut triangles = data.indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect::<Vec<_>>();
+        let count = triangles.len();
+        for i in 0..count {
+            triangles.swap(i, (i * 7919) % count);
+        }
+        data.indices = triangles.concat();
+        let before = triangle_set(&data);
+        let used = data.indices.iter().collect::<std::collections::HashSet<_>>().len();
+
+        data.optimize_vertex_cache();
+        assert_eq!(triangle_set(&data), before);
+        assert_eq!(data.vertex_count(), used);
+        // Vertices are in order of first use
+        let mut next = 0;
+        for i in &data.indices {
+            assert!(*i <= next);
+            if *i == next {
+                next += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_normals() {
+        let mut data = quad();
+        data.weld(0.0);
+        data.compute_smooth_normals();
+        assert!(data.normals.iter().all(|n| *n == [0.0, 0.0, 1.0]));
+
+        // A roof facing +X+Y and +Y, the ridge is shared and the +Y side is larger
+        let mut data = MeshData {
+            positions: vec![[0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [1.0, -1.0, 0.0], [-2.0, 0.0, 0.0]],
+            indices: vec![0, 1, 2, 0, 3, 1],
+            ..Default::default()
+        };
+        data.compute_smooth_normals();
+        assert_eq!(data.normals[2], normalize([1.0, 1.0, 0.0]));
+        assert_eq!(data.normals[3], [0.0, 1.0, 0.0]);
+        let ridge = normalize([1.0, 3.0, 0.0]);
+        assert!(data.normals[..2].iter().all(|n| dot(*n, ridge) > 0.9999));
+    }
+
+    #[test]
+    fn flat_normals() {
+        let mut data = MeshData {
+            positions: vec![[0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [1.0, -1.0, 0.0], [-1.0, 0.0, 0.0]],
+            indices: vec![0, 1, 2, 0, 3, 1],
+            uvs: vec![[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]],
+            ..Default::default()
+        };
+        data.compute_flat_normals();
+        assert_eq!(data.vertex_count(), 6);
+        assert_eq!(data.indices, [0, 1, 2, 3, 4, 5]);
+        assert_eq!(data.positions[3], data.positions[0]);
+        assert_eq!(data.uvs[4], [1.0, 1.0]);
+        assert!(data.normals[..3].iter().all(|n| *n == normalize([1.0, 1.0, 0.0])));
+        assert!(data.normals[3..].iter().all(|n| *n == [0.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn tangents_follow_uvs() {
+        let mut data = MeshData::plane(2.0, 2.0, 2, 2);
+        data.tangents.clear();
+        data.compute_tangents();
+        let close = |a: [f32; 4], b: [f32; 4]| (0..4).all(|i| (a[i] - b[i]).abs() < 1e-5);
+        assert!(data.tangents.iter().all(|t| close(*t, [1.0, 0.0, 0.0, 1.0])), "{:?}", data.tangents);
+
+        // Mirroring u flips the tangent and the handedness
+        for uv in data.uvs.iter_mut() {
+            uv[0] = 1.0 - uv[0];
+        }
+        data.compute_tangents();
+        assert!(data.tangents.iter().all(|t| close(*t, [-1.0, 0.0, 0.0, -1.0])), "{:?}", data.tangents);
+    }
+
+    #[test]
+    fn tangents_fall_back_to_x() {
+        // MikkTSpace gives up without triangles
+        let mut data = quad();
+        data.normals = vec![[0.0, 0.0, 1.0]; 6];
+        data.uvs = vec![[0.0, 0.0]; 6];
+        data.indices.clear();
+        data.compute_tangents();
+        assert_eq!(data.tangents, [[1.0, 0.0, 0.0, 1.0]; 6]);
+    }
+
+    #[test]
+    #[should_panic(expected = "needs normals and uvs")]
+    fn tangents_without_uvs() {
+        let mut data = quad();
+        data.compute_flat_normals();
+        data.compute_tangents();
+    }
+
+    #[test]
+    fn bounds() {
+        let data = MeshData {
+            positions: vec![[1.0, -2.0, 3.0], [-1.0, 4.0, 0.0], [0.0, 0.0, -3.0]],
+            ..Default::default()
+        };
+        let aabb = data.bounding_box();
+        assert_eq!(aabb, Aabb { min: [-1.0, -2.0, -3.0], max: [1.0, 4.0, 3.0] });
+        assert_eq!(aabb.center(), [0.0, 1.0, 0.0]);
+        assert_eq!(aabb.size(), [2.0, 6.0, 6.0]);
+        assert_eq!(MeshData::default().bounding_box(), Aabb { min: [0.0; 3], max: [0.0; 3] });
+
+        let sphere = data.bounding_sphere();
+        assert!(data.positions.iter().all(|p| distance_sq(*p, sphere.center).sqrt() <= sphere.radius + 1e-5));
+        assert_eq!(MeshData::default().bounding_sphere(), BoundingSphere { center: [0.0; 3], radius: 0.0 });
+
+        // The corners of a cube, where the optimal sphere is known
+        let cube = MeshData::cube(2.0);
+        let sphere = cube.bounding_sphere();
+        assert!(cube.positions.iter().all(|p| distance_sq(*p, sphere.center).sqrt() <= sphere.radius + 1e-5));
+        assert!(sphere.radius <= 3.0f32.sqrt() * 1.05);
+    }
+
+    #[test]
+    fn merge_offsets_indices() {
+        let mut data = MeshData::default();
+        data.merge(&quad());
+        assert_eq!(data, quad());
+        data.merge(&quad());
+        assert_eq!(data.vertex_count(), 12);
+        assert_eq!(data.indices[6..], [6, 7, 8, 9, 10, 11]);
+        assert_eq!(data.positions[6..], quad().positions[..]);
+    }
+
+    #[test]
+    #[should_panic(expected = "different attributes")]
+    fn merge_different_attributes() {
+        let mut data = quad();
+        let mut other = quad();
+        other.compute_flat_normals();
+        data.merge(&other);
+    }
+}
@@ -0,0 +1,287 @@
+//! Many meshes sharing one vertex and index buffer, so they can all be drawn with a single
+//! `glMultiDrawElementsIndirect` call. The draw commands live in a `FixedSizeBuffer`, and can be
+//! written by a compute shader doing culling and LOD selection, counting them with an `AtomicCounter`.
+use std::sync::Arc;
+use glow::*;
+
+use super::atomic_counter::AtomicCounter;
+use super::barrier::{HazardTracker, MemoryBarrier, Resource};
+use super::binding::BindingStack;
+use super::buffer::FixedSizeBuffer;
+use super::gl_ext::GlExt;
+use super::mesh::Topology;
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::vertex::{slice_as_bytes, Vertex, VertexStream};
+
+/// A draw command as `glMultiDrawElementsIndirect` reads it.
+/// In GLSL (std430): `struct { uint count; uint instanceCount; uint firstIndex; int baseVertex; uint baseInstance; }`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DrawElementsIndirectCommand {
+    pub count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub base_instance: u32,
+}
+
+/// Where a mesh lives in a `MeshPool`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolMesh {
+    pub first_index: u32,
+    pub index_count: u32,
+    pub base_vertex: i32,
+    pub vertex_count: u32,
+}
+
+impl PoolMesh {
+    /// `base_instance` is where per-instance data for this draw starts, and shows up as `gl_BaseInstance`
+    pub fn command(&self, instance_count: u32, base_instance: u32) -> DrawElementsIndirectCommand {
+        DrawElementsIndirectCommand {
+            count: self.index_count,
+            instance_count,
+            first_index: self.first_index,
+            base_vertex: self.base_vertex,
+            base_instance,
+        }
+    }
+}
+
+/// Vertex and index storage shared by many meshes with the same vertex type.
+/// Capacity is fixed at creation, meshes are appended until `clear`.
+pub struct MeshPool {
+    vao: NativeVertexArray,
+    vbo: NativeBuffer,
+    ebo: NativeBuffer,
+    layout: VertexStream,
+    vertex_capacity: usize,
+    index_capacity: usize,
+    vertex_count: usize,
+    index_count: usize,
+    topology: Topology,
+    gl: Arc<Context>,
+    ext: Arc<GlExt>,
+    tracker: Arc<HazardTracker>,
+    bindings: Arc<BindingStack>,
+    resources: Arc<ResourceRegistry>,
+}
+
+impl Drop for MeshPool {
+    fn drop(&mut self) {
+        self.resources.unregister(ResourceKind::VertexArray, self.vao.0.get());
+        self.resources.unregister(ResourceKind::Buffer, self.vbo.0.get());
+        self.resources.unregister(ResourceKind::Buffer, self.ebo.0.get());
+        unsafe {
+            self.gl.delete_vertex_array(self.vao);
+            self.gl.delete_buffer(self.vbo);
+            self.gl.delete_buffer(self.ebo);
+        }
+    }
+}
+
+impl MeshPool {
+    /// A pool with room for `vertex_capacity` vertices of type `V` and `index_capacity` `u32` indices
+    pub fn new<V: Vertex>(renderer: &super::Renderer, vertex_capacity: usize, index_capacity: usize) -> Self {
+        let layout = V::layout();
+        let gl = renderer.gl.clone();
+        unsafe {
+            let vao = gl.create_vertex_array().expect("Failed to create VAO!");
+            gl.bind_vertex_array(Some(vao));
+
+            let vbo = gl.create_buffer().expect("Failed to create VBO!");
+            gl.bind_buffer(ARRAY_BUFFER, Some(vbo));
+            gl.buffer_data_size(ARRAY_BUFFER, (vertex_capacity * layout.stride) as i32, DYNAMIC_DRAW);
+            layout.apply(&gl);
+
+            let ebo = gl.create_buffer().expect("Failed to create EBO!");
+            gl.bind_buffer(ELEMENT_ARRAY_BUFFER, Some(ebo));
+            gl.buffer_data_size(ELEMENT_ARRAY_BUFFER, (index_capacity * 4) as i32, DYNAMIC_DRAW);
+
+            gl.bind_vertex_array(None);
+            gl.bind_buffer(ARRAY_BUFFER, None);
+
+            renderer.resources.register(ResourceKind::VertexArray, vao.0.get(), 0, "MeshPool");
+            renderer.resources.register(ResourceKind::Buffer, vbo.0.get(), vertex_capacity * layout.stride, "MeshPool vertices");
+            renderer.resources.register(ResourceKind::Buffer, ebo.0.get(), index_capacity * 4, "MeshPool indices");
+
+            Self {
+                vao,
+                vbo,
+                ebo,
+                layout,
+                vertex_capacity,
+                index_capacity,
+                vertex_count: 0,
+                index_count: 0,
+                topology: Topology::Triangles,
+                gl,
+                ext: renderer.ext.clone(),
+                tracker: renderer.tracker.clone(),
+                bindings: renderer.bindings.clone(),
+                resources: renderer.resources.clone(),
+            }
+        }
+    }
+
+    /// Appends a mesh. Indices are relative to its own vertices.
+    /// Panics if `V` isn't the vertex type of the pool, or the pool is full!
+    pub fn add<V: Vertex>(&mut self, vertices: &[V], indices: &[u32]) -> PoolMesh {
+        if V::layout() != self.layout {
+            panic!("Vertex type doesn't match the layout of the mesh pool!");
+        }
+        if self.vertex_count + vertices.len() > self.vertex_capacity || self.index_count + indices.len() > self.index_capacity {
+            panic!("Mesh pool is full! It has room for {} vertices and {} indices.", self.vertex_capacity, self.index_capacity);
+        }
+        unsafe {
+            self.gl.bind_buffer(ARRAY_BUFFER, Some(self.vbo));
+            self.gl.buffer_sub_data_u8_slice(ARRAY_BUFFER, (self.vertex_count * self.layout.stride) as i32, slice_as_bytes(vertices));
+            self.gl.bind_buffer(ARRAY_BUFFER, None);
+            self.gl.bind_vertex_array(Some(self.vao));
+            self.gl.bind_buffer(ELEMENT_ARRAY_BUFFER, Some(self.ebo));
+            self.gl.buffer_sub_data_u8_slice(ELEMENT_ARRAY_BUFFER, (self.index_count * 4) as i32, slice_as_bytes(indices));
+            self.gl.bind_vertex_array(None);
+        }
+        let mesh = PoolMesh {
+            first_index: self.index_count as u32,
+            index_count: indices.len() as u32,
+            base_vertex: self.vertex_count as i32,
+            vertex_count: vertices.len() as u32,
+        };
+        self.vertex_count += vertices.len();
+        self.index_count += indices.len();
+        mesh
+    }
+
+    /// Forgets every mesh, previously returned `PoolMesh`es become invalid
+    pub fn clear(&mut self) {
+        self.vertex_count = 0;
+        self.index_count = 0;
+    }
+
+    /// Defaults to `Triangles`
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    /// Space left, in vertices and indices
+    pub fn remaining(&self) -> (usize, usize) {
+        (self.vertex_capacity - self.vertex_count, self.index_capacity - self.index_count)
+    }
+
+    fn check_shader_bound(&self) {
+        if !self.bindings.program_bound() {
+            panic!("No shader bound! Use `shader.while_bound` or similar!");
+        }
+    }
+
+    /// Draws a single mesh from the pool.
+    /// Panics if no shader is bound!
+    pub fn draw(&self, mesh: PoolMesh) -> Result<(), super::RenderError> {
+        puffin::profile_function!();
+        self.check_shader_bound();
+        self.tracker.issuing_commands();
+        unsafe {
+            self.gl.bind_vertex_array(Some(self.vao));
+            self.gl.draw_elements_base_vertex(self.topology.to_gl(), mesh.index_count as i32, UNSIGNED_INT, (mesh.first_index * 4) as i32, mesh.base_vertex);
+            self.gl.bind_vertex_array(None);
+        }
+        self.tracker.commands_issued();
+        Ok(())
+    }
+}
+
+/// Draw commands for a `MeshPool`, stored on the GPU.
+/// Write them from the CPU with `write_commands`, or from a compute shader by binding `commands()`
+/// with `UniformInterface::bind_storage` and `counter()` with `bind_atomic_counter`, then
+/// doing `commands[atomicCounterIncrement(count)] = ...` for every visible object.
+pub struct MultiDrawIndirect {
+    commands: FixedSizeBuffer<DrawElementsIndirectCommand>,
+    counter: AtomicCounter,
+    max_draws: usize,
+    gl: Arc<Context>,
+    tracker: Arc<HazardTracker>,
+}
+
+impl MultiDrawIndirect {
+    pub fn new(renderer: &super::Renderer, max_draws: usize) -> Self {
+        Self {
+            commands: FixedSizeBuffer::new(renderer, max_draws).with_label("MultiDrawIndirect commands"),
+            counter: AtomicCounter::new(renderer),
+            max_draws,
+            gl: renderer.gl.clone(),
+            tracker: renderer.tracker.clone(),
+        }
+    }
+
+    pub fn commands(&self) -> &FixedSizeBuffer<DrawElementsIndirectCommand> {
+        &self.commands
+    }
+
+    /// Number of commands to draw, read by the GPU when drawing
+    pub fn counter(&self) -> &AtomicCounter {
+        &self.counter
+    }
+
+    pub fn max_draws(&self) -> usize {
+        self.max_draws
+    }
+
+    /// Sets the command count to 0, before a compute pass appends commands
+    pub fn reset(&self) {
+        self.counter.reset(0);
+    }
+
+    /// Replaces the commands from the CPU
+    pub fn write_commands(&self, commands: &[DrawElementsIndirectCommand]) {
+        self.commands.write(0, commands);
+        self.counter.reset(commands.len() as u32);
+    }
+
+    /// Draws every command in one call, up to `max_draws`.
+    /// Panics if no shader is bound, or if the driver lacks glMultiDrawElementsIndirectCount (use `draw_count` there)!
+    pub fn draw(&self, pool: &MeshPool) -> Result<(), super::RenderError> {
+        puffin::profile_function!();
+        pool.check_shader_bound();
+        if !pool.ext.supports_indirect_count() {
+            panic!("glMultiDrawElementsIndirectCount is not supported by this driver, use `draw_count` instead!");
+        }
+        let commands = unsafe { self.commands.buf() };
+        self.tracker.read(Resource::buffer(commands), MemoryBarrier::COMMAND);
+        self.tracker.read(Resource::buffer(self.counter.buf()), MemoryBarrier::COMMAND);
+        self.tracker.issuing_commands();
+        unsafe {
+            self.gl.bind_vertex_array(Some(pool.vao));
+            self.gl.bind_buffer(DRAW_INDIRECT_BUFFER, Some(commands));
+            self.gl.bind_buffer(PARAMETER_BUFFER, Some(self.counter.buf()));
+            let stride = std::mem::size_of::<DrawElementsIndirectCommand>() as i32;
+            pool.ext.multi_draw_elements_indirect_count(pool.topology.to_gl(), UNSIGNED_INT, 0, 0, self.max_draws as i32, stride);
+            self.gl.bind_buffer(PARAMETER_BUFFER, None);
+            self.gl.bind_buffer(DRAW_INDIRECT_BUFFER, None);
+            self.gl.bind_vertex_array(None);
+        }
+        self.tracker.commands_issued();
+        Ok(())
+    }
+
+    /// Draws the first `count` commands, without reading the counter
+    pub fn draw_count(&self, pool: &MeshPool, count: usize) -> Result<(), super::RenderError> {
+        puffin::profile_function!();
+        pool.check_shader_bound();
+        if count > self.max_draws {
+            panic!("Cannot draw {} commands, the buffer only holds {}!", count, self.max_draws);
+        }
+        let commands = unsafe { self.commands.buf() };
+        self.tracker.read(Resource::buffer(commands), MemoryBarrier::COMMAND);
+        self.tracker.issuing_commands();
+        unsafe {
+            self.gl.bind_vertex_array(Some(pool.vao));
+            self.gl.bind_buffer(DRAW_INDIRECT_BUFFER, Some(commands));
+            let stride = std::mem::size_of::<DrawElementsIndirectCommand>() as i32;
+            pool.ext.multi_draw_elements_indirect(pool.topology.to_gl(), UNSIGNED_INT, 0, count as i32, stride);
+            self.gl.bind_buffer(DRAW_INDIRECT_BUFFER, None);
+            self.gl.bind_vertex_array(None);
+        }
+        self.tracker.commands_issued();
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
-use std::sync::Arc;
-use std::sync::atomic::AtomicBool;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use winit::window::Window;
 use raw_gl_context::{GlConfig, GlContext};
@@ -11,6 +11,32 @@ pub mod shader;
 pub mod buffer;
 pub mod atomic_counter;
 pub mod texture;
+pub mod sampler;
+pub mod layered_texture;
+pub mod diagnostics;
+pub mod barrier;
+pub mod reflection;
+pub mod spirv;
+pub mod state;
+pub mod vertex;
+pub mod mesh_pool;
+pub mod obj;
+pub mod model;
+pub mod mesh_data;
+pub mod resources;
+
+mod binding;
+mod primitives;
+pub use primitives::{Shape, ShapeVertex};
+#[cfg(feature = "validation")]
+pub mod validation;
+#[cfg(feature = "gltf")]
+pub mod scene;
+#[cfg(feature = "image")]
+pub mod texture_loader;
+
+mod gl_ext;
+mod program_cache;
 
 #[derive(Debug)]
 pub enum RenderError {
@@ -45,7 +71,12 @@ pub struct Renderer {
     pub(crate) context: GlContext,
     pub(crate) is_context_current: bool,
     pub gl: Arc<Context>,
-    pub(crate) shader_bound: Arc<AtomicBool>,
+    pub(crate) bindings: Arc<binding::BindingStack>,
+    pub(crate) ext: Arc<gl_ext::GlExt>,
+    pub(crate) tracker: Arc<barrier::HazardTracker>,
+    pub(crate) state: Arc<state::StateCache>,
+    pub(crate) program_cache: Mutex<Option<program_cache::ProgramCache>>,
+    pub(crate) resources: Arc<resources::ResourceRegistry>,
 
     pub(crate) default_fb_shader: Arc<shader::Shader>,
 }
@@ -63,16 +94,25 @@ impl Renderer {
             let gl = Context::from_loader_function(|symbol| context.get_proc_address(symbol) as *const _);
             Arc::new(gl)
         };
-        let shader_bound = Arc::new(AtomicBool::new(false));
+        let ext = Arc::new(gl_ext::GlExt::load(|symbol| context.get_proc_address(symbol) as *const _));
+        let bindings = Arc::new(binding::BindingStack::new(gl.clone()));
+        let tracker = Arc::new(barrier::HazardTracker::new(gl.clone()));
+        let state = Arc::new(state::StateCache::new(gl.clone()));
+        let resources = Arc::new(resources::ResourceRegistry::new(gl.clone()));
 
-        let default_fb_shader = shader::Shader::new_from_gl(gl.clone(), shader_bound.clone(), VS, "foxtail_default_fb_vs.glsl", FB_FS, "foxtail_default_fb_fs.glsl");
+        let default_fb_shader = shader::Shader::new_from_gl(gl.clone(), &ext, tracker.clone(), bindings.clone(), resources.clone(), (VS, "foxtail_default_fb_vs.glsl"), (FB_FS, "foxtail_default_fb_fs.glsl"));
 
         Self {
             size: size,
             context: context,
             is_context_current: true,
             gl: gl,
-            shader_bound: shader_bound,
+            bindings,
+            ext,
+            tracker,
+            state,
+            program_cache: Mutex::new(None),
+            resources,
 
             default_fb_shader: Arc::new(default_fb_shader),
         }
@@ -101,10 +141,46 @@ impl Renderer {
         }
     }
 
-    pub fn fence(&self) {
-        unsafe {
-            self.gl.memory_barrier(glow::ALL_BARRIER_BITS);
+    /// Caches linked shader programs in `dir`, so later launches can skip compiling them.
+    /// Does nothing if the driver doesn't support program binaries.
+    pub fn enable_program_cache<P: Into<PathBuf>>(&self, dir: P) {
+        let formats = unsafe { self.gl.get_parameter_i32(NUM_PROGRAM_BINARY_FORMATS) };
+        if !self.ext.supports_program_binary() || formats <= 0 {
+            warn!("Program binaries are not supported by this driver, not enabling the program cache!");
+            return;
         }
+        *self.program_cache.lock().unwrap() = Some(program_cache::ProgramCache::new(&self.gl, self.ext.clone(), dir.into()));
+    }
+
+    pub fn disable_program_cache(&self) {
+        *self.program_cache.lock().unwrap() = None;
+    }
+
+    /// Live GL objects with their sizes and labels
+    pub fn resources(&self) -> &resources::ResourceRegistry {
+        &self.resources
+    }
+
+    /// Issues `memory_barrier(ALL_BARRIER_BITS)`, see `barrier` for something cheaper
+    pub fn fence(&self) {
+        self.tracker.barrier(barrier::MemoryBarrier::ALL);
+    }
+
+    /// Makes earlier shader writes visible to the kinds of access in `barrier`
+    pub fn barrier(&self, barrier: barrier::MemoryBarrier) {
+        self.tracker.barrier(barrier);
+    }
+
+    /// When enabled, `FixedSizeBuffer`, `AtomicCounter` and image bindings through `Texture` record
+    /// shader writes, and the barrier a later read needs is inserted automatically.
+    pub fn enable_hazard_tracking(&self, enabled: bool) {
+        self.tracker.set_enabled(enabled);
+    }
+
+    /// Runs a closure with `state` applied, restoring the previous state afterwards
+    pub fn with_state<F: FnOnce() -> Result<(), RenderError>>(&self, state: &state::RenderState, f: F) -> Result<(), RenderError> {
+        let _guard = self.state.push(state);
+        f()
     }
 
     pub fn start_frame(&mut self) -> Result<(), RenderError> {
@@ -124,3 +200,13 @@ impl Renderer {
         Ok(())
     }
 }
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        // The default framebuffer shader is deleted right after this, it doesn't count as a leak
+        self.default_fb_shader.forget_resource();
+        if let Some(report) = self.resources.leak_report() {
+            warn!("{}", report);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use super::mesh::{Indices, Mesh, MeshRange};
+use super::obj::{load_obj, Material, ModelVertex, ObjError, ObjModel, Submesh};
+use super::vertex::{slice_as_bytes, Vertex, VertexLayout};
+
+/// A mesh split into submeshes, each with its own material
+pub struct Model {
+    mesh: Mesh,
+    submeshes: Vec<Submesh>,
+    materials: Vec<Material>,
+}
+
+impl super::Drawable for Model {
+    /// Draws every submesh. Panics if no shader is bound!
+    fn draw(&self) -> Result<(), super::RenderError> {
+        self.mesh.draw()
+    }
+}
+
+impl Model {
+    pub fn from_obj<P: AsRef<Path>>(renderer: &super::Renderer, path: P) -> Result<Self, ObjError> {
+        Ok(Self::from_obj_model(renderer, load_obj(path)?))
+    }
+
+    pub fn from_obj_model(renderer: &super::Renderer, model: ObjModel) -> Self {
+        Self {
+            mesh: mesh_from_obj_model(renderer, &model),
+            submeshes: model.submeshes,
+            materials: model.materials,
+        }
+    }
+
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+
+    pub fn submeshes(&self) -> &[Submesh] {
+        &self.submeshes
+    }
+
+    pub fn materials(&self) -> &[Material] {
+        &self.materials
+    }
+
+    pub fn material(&self, submesh: usize) -> Option<&Material> {
+        self.submeshes[submesh].material.map(|m| &self.materials[m])
+    }
+
+    /// Draws one submesh, to set material uniforms in between.
+    /// Panics if no shader is bound!
+    pub fn draw_submesh(&self, submesh: usize) -> Result<(), super::RenderError> {
+        let submesh = &self.submeshes[submesh];
+        self.mesh.draw_range(MeshRange {
+            first: submesh.first_index,
+            count: submesh.index_count,
+            base_vertex: 0,
+        })
+    }
+}
+
+fn mesh_from_obj_model(renderer: &super::Renderer, model: &ObjModel) -> Mesh {
+    let layout = VertexLayout::interleaved(ModelVertex::layout());
+    Mesh::from_layout(renderer, &layout, &[slice_as_bytes(&model.vertices)], Indices::U32(&model.indices))
+}
+
+impl Mesh {
+    /// Loads an OBJ file as a single mesh with `ModelVertex` vertices, ignoring materials.
+    /// See `Model::from_obj` to keep the submeshes.
+    pub fn from_obj<P: AsRef<Path>>(renderer: &super::Renderer, path: P) -> Result<Self, ObjError> {
+        let model = super::obj::parse_obj(&super::obj::read(path.as_ref())?)?;
+        Ok(mesh_from_obj_model(renderer, &model))
+    }
+}
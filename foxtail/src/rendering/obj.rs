@@ -0,0 +1,462 @@
+//! Wavefront OBJ and MTL parsing. Everything here runs on the CPU, `Model::from_obj` uploads the result.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::mesh_data::{add, cross, normalize, sub, MeshData};
+use super::vertex::{AttribType, Vertex, VertexStream};
+
+#[derive(Debug)]
+pub struct ObjError {
+    /// 1-based, `None` for errors that aren't about a specific line
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ObjError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+fn error<T>(line: usize, message: String) -> Result<T, ObjError> {
+    Err(ObjError { line: Some(line), message })
+}
+
+/// Position at location 0, normal at 1, uv at 2
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ModelVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+unsafe impl Vertex for ModelVertex {
+    fn layout() -> VertexStream {
+        VertexStream::new(std::mem::size_of::<Self>())
+            .float(0, AttribType::F32, 3, std::mem::offset_of!(Self, position))
+            .float(1, AttribType::F32, 3, std::mem::offset_of!(Self, normal))
+            .float(2, AttribType::F32, 2, std::mem::offset_of!(Self, uv))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub name: String,
+    /// `Kd`
+    pub diffuse: [f32; 3],
+    /// `Ks`
+    pub specular: [f32; 3],
+    /// `Ns`
+    pub shininess: f32,
+    /// `d`, or `1 - Tr`
+    pub opacity: f32,
+    /// `map_Kd`
+    pub diffuse_texture: Option<PathBuf>,
+    /// `norm`, `map_Bump` or `bump`
+    pub normal_texture: Option<PathBuf>,
+    /// `map_Ks`
+    pub specular_texture: Option<PathBuf>,
+}
+
+impl Material {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            diffuse: [1.0; 3],
+            specular: [0.0; 3],
+            shininess: 0.0,
+            opacity: 1.0,
+            diffuse_texture: None,
+            normal_texture: None,
+            specular_texture: None,
+        }
+    }
+}
+
+/// A range of indices drawn with one material
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Submesh {
+    /// Index into `ObjModel::materials`, `None` for faces before any `usemtl` or with an unknown material
+    pub material: Option<usize>,
+    pub first_index: u32,
+    pub index_count: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ObjModel {
+    pub vertices: Vec<ModelVertex>,
+    pub indices: Vec<u32>,
+    /// One per material, in the order materials are first used
+    pub submeshes: Vec<Submesh>,
+    pub materials: Vec<Material>,
+    /// `mtllib` statements, as written in the file
+    pub material_libs: Vec<String>,
+}
+
+impl ObjModel {
+    /// All submeshes as one `MeshData`, in the same vertex and index order
+    pub fn mesh_data(&self) -> MeshData {
+        MeshData {
+            positions: self.vertices.iter().map(|v| v.position).collect(),
+            normals: self.vertices.iter().map(|v| v.normal).collect(),
+            uvs: self.vertices.iter().map(|v| v.uv).collect(),
+            indices: self.indices.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+fn parse_floats<const N: usize>(line: usize, args: &[&str], min: usize) -> Result<[f32; N], ObjError> {
+    if args.len() < min {
+        return error(line, format!("Expected at least {} numbers, found {}", min, args.len()));
+    }
+    let mut out = [0.0; N];
+    for (i, arg) in args.iter().take(N).enumerate() {
+        out[i] = match arg.parse() {
+            Ok(v) => v,
+            Err(_) => return error(line, format!("`{}` is not a number", arg)),
+        };
+    }
+    Ok(out)
+}
+
+/// Resolves a 1-based or negative (relative to the end) OBJ index
+fn resolve_index(line: usize, s: &str, len: usize) -> Result<usize, ObjError> {
+    let i: i64 = match s.parse() {
+        Ok(i) => i,
+        Err(_) => return error(line, format!("`{}` is not an index", s)),
+    };
+    let resolved = if i < 0 { len as i64 + i } else { i - 1 };
+    if resolved < 0 || resolved >= len as i64 {
+        return error(line, format!("Index {} is out of range", i));
+    }
+    Ok(resolved as usize)
+}
+
+/// Position, uv and normal indices of a face corner
+type Corner = (usize, Option<usize>, Option<usize>);
+
+/// Parses `v`, `v/vt`, `v//vn` and `v/vt/vn`
+fn parse_corner(line: usize, s: &str, positions: usize, uvs: usize, normals: usize) -> Result<Corner, ObjError> {
+    let mut parts = s.split('/');
+    let v = resolve_index(line, parts.next().unwrap_or(""), positions)?;
+    let vt = match parts.next() {
+        Some(p) if !p.is_empty() => Some(resolve_index(line, p, uvs)?),
+        _ => None,
+    };
+    let vn = match parts.next() {
+        Some(p) if !p.is_empty() => Some(resolve_index(line, p, normals)?),
+        _ => None,
+    };
+    Ok((v, vt, vn))
+}
+
+/// Parses an OBJ file. Faces are triangulated as fans, identical corners share a vertex,
+/// and vertices without a normal get a smooth one from the faces around their position.
+/// `mtllib`s are only listed, use `parse_mtl` or `load_obj` to get the materials.
+pub fn parse_obj(src: &str) -> Result<ObjModel, ObjError> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+
+    let mut model = ObjModel::default();
+    let mut corners: HashMap<Corner, u32> = HashMap::new();
+    // Indices per material name, in order of first use
+    let mut groups: Vec<(Option<String>, Vec<u32>)> = vec![(None, Vec::new())];
+    let mut current = 0;
+    // Vertices that need a generated normal, by position index
+    let mut missing_normals: Vec<(u32, usize)> = Vec::new();
+    let mut face_normals: HashMap<usize, [f32; 3]> = HashMap::new();
+
+    for (i, raw) in src.lines().enumerate() {
+        let line = i + 1;
+        let raw = raw.split('#').next().unwrap_or("");
+        let mut words = raw.split_whitespace();
+        let keyword = match words.next() {
+            Some(k) => k,
+            None => continue,
+        };
+        let args = words.collect::<Vec<&str>>();
+        match keyword {
+            "v" => positions.push(parse_floats(line, &args, 3)?),
+            "vt" => uvs.push(parse_floats(line, &args, 1)?),
+            "vn" => normals.push(parse_floats(line, &args, 3)?),
+            "f" => {
+                if args.len() < 3 {
+                    return error(line, format!("Faces need at least 3 corners, found {}", args.len()));
+                }
+                let face = args.iter().map(|a| parse_corner(line, a, positions.len(), uvs.len(), normals.len())).collect::<Result<Vec<Corner>, ObjError>>()?;
+                // Area weighted, so bigger faces count more towards generated normals
+                let mut face_normal = [0.0; 3];
+                for j in 1..face.len() - 1 {
+                    let n = cross(sub(positions[face[j].0], positions[face[0].0]), sub(positions[face[j + 1].0], positions[face[0].0]));
+                    face_normal = add(face_normal, n);
+                }
+
+                let mut face_indices = Vec::with_capacity(face.len());
+                for corner in &face {
+                    if corner.2.is_none() {
+                        let acc = face_normals.entry(corner.0).or_insert([0.0; 3]);
+                        *acc = add(*acc, face_normal);
+                    }
+                    let index = *corners.entry(*corner).or_insert_with(|| {
+                        let uv = corner.1.map_or([0.0; 2], |t| uvs[t]);
+                        model.vertices.push(ModelVertex {
+                            position: positions[corner.0],
+                            normal: corner.2.map_or([0.0; 3], |n| normals[n]),
+                            uv: [uv[0], uv[1]],
+                        });
+                        let index = model.vertices.len() as u32 - 1;
+                        if corner.2.is_none() {
+                            missing_normals.push((index, corner.0));
+                        }
+                        index
+                    });
+                    face_indices.push(index);
+                }
+                for j in 1..face_indices.len() - 1 {
+                    groups[current].1.extend_from_slice(&[face_indices[0], face_indices[j], face_indices[j + 1]]);
+                }
+            },
+            "usemtl" => {
+                let name = args.join(" ");
+                current = match groups.iter().position(|(m, _)| m.as_deref() == Some(name.as_str())) {
+                    Some(g) => g,
+                    None => {
+                        groups.push((Some(name), Vec::new()));
+                        groups.len() - 1
+                    }
+                };
+            },
+            "mtllib" => model.material_libs.push(args.join(" ")),
+            // Groups, objects, smoothing groups and the rest don't affect the mesh
+            _ => {},
+        }
+    }
+
+    for (index, position) in missing_normals {
+        // Only degenerate faces around this position, point it up
+        let normal = face_normals[&position];
+        model.vertices[index as usize].normal = if normal == [0.0; 3] { [0.0, 1.0, 0.0] } else { normalize(normal) };
+    }
+
+    for (material, indices) in groups {
+        if indices.is_empty() {
+            continue;
+        }
+        let material = material.map(|name| {
+            model.materials.push(Material::new(&name));
+            model.materials.len() - 1
+        });
+        model.submeshes.push(Submesh {
+            material,
+            first_index: model.indices.len() as u32,
+            index_count: indices.len() as u32,
+        });
+        model.indices.extend(indices);
+    }
+
+    Ok(model)
+}
+
+/// Texture statements can have options before the path, like `map_Kd -s 1 1 1 tex.png`.
+/// Takes the last argument, so paths with spaces are not supported.
+fn texture_path(line: usize, args: &[&str]) -> Result<PathBuf, ObjError> {
+    match args.last() {
+        Some(path) => Ok(PathBuf::from(path.replace('\\', "/"))),
+        None => error(line, "Missing texture path".to_string()),
+    }
+}
+
+/// Parses an MTL file. Texture paths are kept as written.
+pub fn parse_mtl(src: &str) -> Result<Vec<Material>, ObjError> {
+    let mut materials: Vec<Material> = Vec::new();
+    for (i, raw) in src.lines().enumerate() {
+        let line = i + 1;
+        let raw = raw.split('#').next().unwrap_or("");
+        let mut words = raw.split_whitespace();
+        let keyword = match words.next() {
+            Some(k) => k,
+            None => continue,
+        };
+        let args = words.collect::<Vec<&str>>();
+        if keyword == "newmtl" {
+            materials.push(Material::new(&args.join(" ")));
+            continue;
+        }
+        let material = match materials.last_mut() {
+            Some(m) => m,
+            None => return error(line, format!("`{}` before any `newmtl`", keyword)),
+        };
+        match keyword {
+            "Kd" => material.diffuse = parse_floats(line, &args, 3)?,
+            "Ks" => material.specular = parse_floats(line, &args, 3)?,
+            "Ns" => material.shininess = parse_floats::<1>(line, &args, 1)?[0],
+            "d" => material.opacity = parse_floats::<1>(line, &args, 1)?[0],
+            "Tr" => material.opacity = 1.0 - parse_floats::<1>(line, &args, 1)?[0],
+            "map_Kd" => material.diffuse_texture = Some(texture_path(line, &args)?),
+            "map_Ks" => material.specular_texture = Some(texture_path(line, &args)?),
+            "norm" | "map_Bump" | "map_bump" | "bump" => material.normal_texture = Some(texture_path(line, &args)?),
+            _ => {},
+        }
+    }
+    Ok(materials)
+}
+
+pub(super) fn read(path: &Path) -> Result<String, ObjError> {
+    std::fs::read_to_string(path).map_err(|e| ObjError { line: None, message: format!("Failed to read `{}`: {}", path.display(), e) })
+}
+
+/// Reads an OBJ file and its MTL files. Texture paths are made relative to the working directory,
+/// like `path` is. MTL files that can't be read are skipped with a warning,
+/// materials missing from every MTL file keep the defaults.
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<ObjModel, ObjError> {
+    let path = path.as_ref();
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut model = parse_obj(&read(path)?)?;
+
+    let mut library = Vec::new();
+    for lib in &model.material_libs {
+        let lib_path = dir.join(lib);
+        let src = match read(&lib_path) {
+            Ok(src) => src,
+            Err(e) => {
+                warn!("{}, its materials keep the defaults", e);
+                continue;
+            }
+        };
+        let materials = parse_mtl(&src).map_err(|e| ObjError { line: e.line, message: format!("{}: {}", lib_path.display(), e.message) })?;
+        library.extend(materials);
+    }
+
+    for material in &mut model.materials {
+        match library.iter().find(|m| m.name == material.name) {
+            Some(found) => {
+                *material = found.clone();
+                for tex in [&mut material.diffuse_texture, &mut material.normal_texture, &mut material.specular_texture].into_iter().flatten() {
+                    *tex = dir.join(&tex);
+                }
+            },
+            None => warn!("Material `{}` used in `{}` was not found in any MTL file!", material.name, path.display()),
+        }
+    }
+
+    Ok(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quad_fan() {
+        let model = parse_obj("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n").unwrap();
+        assert_eq!(model.vertices.len(), 4);
+        assert_eq!(model.indices, [0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn negative_indices() {
+        let model = parse_obj("v 0 0 0\nv 1 0 0\nv 1 1 0\nvt 0.5 0.25\nf -3/-1 -2/-1 -1/-1\n").unwrap();
+        assert_eq!(model.vertices[0].position, [0.0, 0.0, 0.0]);
+        assert_eq!(model.vertices[2].position, [1.0, 1.0, 0.0]);
+        assert!(model.vertices.iter().all(|v| v.uv == [0.5, 0.25]));
+        assert!(parse_obj("v 0 0 0\nf -2 -1 -1\n").is_err());
+    }
+
+    #[test]
+    fn shared_corners() {
+        // Corner 1/1 and 3/3 are used by both triangles, 2/2 and 2/4 differ in uv
+        let src = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0
+vt 0 0\nvt 1 0\nvt 1 1\nvt 0.5 0
+f 1/1 2/2 3/3\nf 1/1 3/3 2/4\nf 1/1 3/3 4/1\n";
+        let model = parse_obj(src).unwrap();
+        assert_eq!(model.vertices.len(), 5);
+        assert_eq!(model.indices, [0, 1, 2, 0, 2, 3, 0, 2, 4]);
+    }
+
+    #[test]
+    fn submesh_per_material() {
+        let src = "v 0 0 0\nv 1 0 0\nv 1 1 0
+f 1 2 3
+usemtl red
+f 1 2 3
+usemtl blue
+f 1 2 3
+usemtl red
+f 1 2 3
+";
+        let model = parse_obj(src).unwrap();
+        assert_eq!(model.materials.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), ["red", "blue"]);
+        assert_eq!(model.submeshes, [
+            Submesh { material: None, first_index: 0, index_count: 3 },
+            Submesh { material: Some(0), first_index: 3, index_count: 6 },
+            Submesh { material: Some(1), first_index: 9, index_count: 3 },
+        ]);
+    }
+
+    #[test]
+    fn generated_normals() {
+        // Two faces meeting at a right angle, the shared edge gets the average
+        let src = "v 0 0 0\nv 1 0 0\nv 1 0 -1\nv 0 1 0\nvn 1 0 0
+f 1 2 3
+f 1 2 4
+f 1//1 2//1 4//1
+";
+        let model = parse_obj(src).unwrap();
+        let s = std::f32::consts::FRAC_1_SQRT_2;
+        let close = |a: [f32; 3], b: [f32; 3]| (0..3).all(|i| (a[i] - b[i]).abs() < 1e-6);
+        assert!(close(model.vertices[0].normal, [0.0, s, s]));
+        assert!(close(model.vertices[2].normal, [0.0, 1.0, 0.0]));
+        assert!(close(model.vertices[3].normal, [0.0, 0.0, 1.0]));
+        // Explicit normals are kept
+        assert_eq!(model.vertices.len(), 7);
+        assert!(model.vertices[4..].iter().all(|v| v.normal == [1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn mtl() {
+        let src = "newmtl shiny
+Kd 0.5 0.25 1
+Ks 1 1 1
+Ns 64
+Tr 0.25
+map_Kd -s 1 1 1 textures\\albedo.png
+map_Bump normal.png
+newmtl glass
+d 0.1
+";
+        let materials = parse_mtl(src).unwrap();
+        assert_eq!(materials.len(), 2);
+        let shiny = &materials[0];
+        assert_eq!(shiny.name, "shiny");
+        assert_eq!(shiny.diffuse, [0.5, 0.25, 1.0]);
+        assert_eq!(shiny.specular, [1.0; 3]);
+        assert_eq!(shiny.shininess, 64.0);
+        assert_eq!(shiny.opacity, 0.75);
+        assert_eq!(shiny.diffuse_texture, Some(PathBuf::from("textures/albedo.png")));
+        assert_eq!(shiny.normal_texture, Some(PathBuf::from("normal.png")));
+        assert_eq!(shiny.specular_texture, None);
+        assert_eq!(materials[1].opacity, 0.1);
+        assert_eq!(materials[1].diffuse, [1.0; 3]);
+        assert!(parse_mtl("Kd 1 1 1\n").is_err());
+    }
+
+    #[test]
+    fn missing_mtl() {
+        let path = std::env::temp_dir().join(format!("foxtail_missing_mtl_{}.obj", std::process::id()));
+        std::fs::write(&path, "mtllib missing.mtl\nv 0 0 0\nv 1 0 0\nv 1 1 0\nusemtl red\nf 1 2 3\n").unwrap();
+        let model = load_obj(&path);
+        std::fs::remove_file(&path).unwrap();
+        let model = model.unwrap();
+        assert_eq!(model.materials.len(), 1);
+        assert_eq!(model.materials[0].name, "red");
+        assert_eq!(model.materials[0].diffuse, Material::new("red").diffuse);
+    }
+}
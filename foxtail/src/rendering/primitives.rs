@@ -0,0 +1,492 @@
+//! Procedural shapes. Everything is centered on the origin with +Y up,
+//! counter-clockwise front faces and `v` increasing upwards.
+use std::f32::consts::{PI, TAU};
+
+use super::mesh::Mesh;
+use super::mesh_data::{add, normalize, scale, MeshData};
+use super::vertex::{AttribType, Vertex, VertexStream};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ShapeVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+    /// Points along `+u`, `w` is the handedness of the bitangent (`cross(normal, tangent) * w`)
+    pub tangent: [f32; 4],
+}
+
+unsafe impl Vertex for ShapeVertex {
+    fn layout() -> VertexStream {
+        VertexStream::new(std::mem::size_of::<Self>())
+            .float(0, AttribType::F32, 3, std::mem::offset_of!(Self, position))
+            .float(1, AttribType::F32, 3, std::mem::offset_of!(Self, normal))
+            .float(2, AttribType::F32, 2, std::mem::offset_of!(Self, uv))
+            .float(3, AttribType::F32, 4, std::mem::offset_of!(Self, tangent))
+    }
+}
+
+/// Indexed triangle list with interleaved vertices, the shapes below are built as `MeshData`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Shape {
+    pub vertices: Vec<ShapeVertex>,
+    pub indices: Vec<u32>,
+}
+
+/// Attributes `data` doesn't have are zeroed
+impl From<MeshData> for Shape {
+    fn from(data: MeshData) -> Self {
+        let vertices = (0..data.vertex_count()).map(|i| ShapeVertex {
+            position: data.positions[i],
+            normal: data.normals.get(i).copied().unwrap_or_default(),
+            uv: data.uvs.get(i).copied().unwrap_or_default(),
+            tangent: data.tangents.get(i).copied().unwrap_or_default(),
+        }).collect();
+        Self { vertices, indices: data.indices }
+    }
+}
+
+impl From<&Shape> for MeshData {
+    fn from(shape: &Shape) -> Self {
+        MeshData {
+            positions: shape.vertices.iter().map(|v| v.position).collect(),
+            normals: shape.vertices.iter().map(|v| v.normal).collect(),
+            uvs: shape.vertices.iter().map(|v| v.uv).collect(),
+            tangents: shape.vertices.iter().map(|v| v.tangent).collect(),
+            indices: shape.indices.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Shape {
+    pub fn cube(size: f32) -> Self {
+        MeshData::cube(size).into()
+    }
+
+    pub fn plane(width: f32, depth: f32, subdivisions_x: u32, subdivisions_z: u32) -> Self {
+        MeshData::plane(width, depth, subdivisions_x, subdivisions_z).into()
+    }
+
+    pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Self {
+        MeshData::uv_sphere(radius, segments, rings).into()
+    }
+
+    pub fn icosphere(radius: f32, subdivisions: u32) -> Self {
+        MeshData::icosphere(radius, subdivisions).into()
+    }
+
+    pub fn cylinder(radius: f32, height: f32, segments: u32) -> Self {
+        MeshData::cylinder(radius, height, segments).into()
+    }
+
+    pub fn cone(radius: f32, height: f32, segments: u32) -> Self {
+        MeshData::cone(radius, height, segments).into()
+    }
+
+    pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Self {
+        MeshData::capsule(radius, height, segments, rings).into()
+    }
+
+    pub fn torus(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> Self {
+        MeshData::torus(major_radius, minor_radius, major_segments, minor_segments).into()
+    }
+}
+
+impl MeshData {
+    pub fn cube(size: f32) -> Self {
+        let faces = [
+            ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+            ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+            ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+            ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+            ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ];
+        let mut shape = MeshData::default();
+        for (normal, u, v) in faces {
+            shape.merge(&grid(scale(normal, size * 0.5), scale(u, size), scale(v, size), normal, 1, 1));
+        }
+        shape.compute_tangents();
+        shape
+    }
+
+    /// Flat grid on the XZ plane facing +Y, split into `subdivisions_x * subdivisions_z` quads
+    pub fn plane(width: f32, depth: f32, subdivisions_x: u32, subdivisions_z: u32) -> Self {
+        if subdivisions_x == 0 || subdivisions_z == 0 {
+            panic!("A plane needs at least one subdivision on each axis!");
+        }
+        let mut shape = grid([0.0; 3], [width, 0.0, 0.0], [0.0, 0.0, -depth], [0.0, 1.0, 0.0], subdivisions_x, subdivisions_z);
+        shape.compute_tangents();
+        shape
+    }
+
+    /// Sphere made of `rings` latitude bands with `segments` quads each
+    pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Self {
+        if segments < 3 || rings < 2 {
+            panic!("A sphere needs at least 3 segments and 2 rings!");
+        }
+        let profile = (0..=rings).map(|i| {
+            let lat = -PI / 2.0 + PI * i as f32 / rings as f32;
+            let (r, y) = (lat.cos(), lat.sin());
+            [r * radius, y * radius, r, y, i as f32 / rings as f32]
+        }).collect::<Vec<[f32; 5]>>();
+        let mut shape = lathe(&profile, segments);
+        shape.compute_tangents();
+        shape
+    }
+
+    /// Subdivided icosahedron, with evenly sized triangles. `0` subdivisions is the plain icosahedron
+    pub fn icosphere(radius: f32, subdivisions: u32) -> Self {
+        let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+        let mut points = vec![
+            [-1.0, t, 0.0], [1.0, t, 0.0], [-1.0, -t, 0.0], [1.0, -t, 0.0],
+            [0.0, -1.0, t], [0.0, 1.0, t], [0.0, -1.0, -t], [0.0, 1.0, -t],
+            [t, 0.0, -1.0], [t, 0.0, 1.0], [-t, 0.0, -1.0], [-t, 0.0, 1.0],
+        ].into_iter().map(normalize).collect::<Vec<[f32; 3]>>();
+        let mut triangles: Vec<[u32; 3]> = vec![
+            [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+            [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+            [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+            [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+        ];
+
+        for _ in 0..subdivisions {
+            let mut midpoints = std::collections::HashMap::new();
+            let mut midpoint = |a: u32, b: u32, points: &mut Vec<[f32; 3]>| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    points.push(normalize(scale(add(points[a as usize], points[b as usize]), 0.5)));
+                    points.len() as u32 - 1
+                })
+            };
+            triangles = triangles.into_iter().flat_map(|[a, b, c]| {
+                let ab = midpoint(a, b, &mut points);
+                let bc = midpoint(b, c, &mut points);
+                let ca = midpoint(c, a, &mut points);
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            }).collect();
+        }
+
+        // Spherical UVs, with vertices along the seam and at the poles split so every triangle maps continuously
+        let uv = |p: [f32; 3]| [0.5 + p[0].atan2(p[2]) / TAU, 0.5 + p[1].clamp(-1.0, 1.0).asin() / PI];
+        let mut shape = MeshData::default();
+        for triangle in triangles {
+            let mut uvs = triangle.map(|i| uv(points[i as usize]));
+            let max_u = uvs.iter().map(|uv| uv[0]).fold(f32::MIN, f32::max);
+            for uv in uvs.iter_mut() {
+                if max_u - uv[0] > 0.5 {
+                    uv[0] += 1.0;
+                }
+            }
+            for i in 0..3 {
+                if points[triangle[i] as usize][1].abs() > 0.9999 {
+                    uvs[i][0] = (uvs[(i + 1) % 3][0] + uvs[(i + 2) % 3][0]) / 2.0;
+                }
+            }
+            for i in 0..3 {
+                let normal = points[triangle[i] as usize];
+                shape.indices.push(shape.positions.len() as u32);
+                shape.push_vertex(scale(normal, radius), normal, uvs[i]);
+            }
+        }
+        shape.weld(0.0);
+        shape.compute_tangents();
+        shape
+    }
+
+    /// Closed cylinder along Y
+    pub fn cylinder(radius: f32, height: f32, segments: u32) -> Self {
+        if segments < 3 {
+            panic!("A cylinder needs at least 3 segments!");
+        }
+        let h = height / 2.0;
+        let mut shape = lathe(&[[radius, -h, 1.0, 0.0, 0.0], [radius, h, 1.0, 0.0, 1.0]], segments);
+        shape.merge(&disc(radius, h, segments, true));
+        shape.merge(&disc(radius, -h, segments, false));
+        shape.compute_tangents();
+        shape
+    }
+
+    /// Cone along Y with its tip at `height / 2`
+    pub fn cone(radius: f32, height: f32, segments: u32) -> Self {
+        if segments < 3 {
+            panic!("A cone needs at least 3 segments!");
+        }
+        let h = height / 2.0;
+        let slant = (height * height + radius * radius).sqrt();
+        let (nr, ny) = (height / slant, radius / slant);
+        let mut shape = lathe(&[[radius, -h, nr, ny, 0.0], [0.0, h, nr, ny, 1.0]], segments);
+        shape.merge(&disc(radius, -h, segments, false));
+        shape.compute_tangents();
+        shape
+    }
+
+    /// Cylinder along Y capped by hemispheres. `height` is the length of the cylindrical part,
+    /// each hemisphere has `rings` latitude bands
+    pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Self {
+        if segments < 3 || rings < 1 {
+            panic!("A capsule needs at least 3 segments and 1 ring!");
+        }
+        let h = height / 2.0;
+        let length = PI * radius + height;
+        let mut profile = Vec::new();
+        for (offset, start) in [(-h, -PI / 2.0), (h, 0.0)] {
+            for i in 0..=rings {
+                let lat = start + PI / 2.0 * i as f32 / rings as f32;
+                let (r, y) = (lat.cos(), lat.sin());
+                let arc = (lat + PI / 2.0) * radius + if offset > 0.0 { height } else { 0.0 };
+                profile.push([r * radius, y * radius + offset, r, y, arc / length]);
+            }
+        }
+        let mut shape = lathe(&profile, segments);
+        shape.compute_tangents();
+        shape
+    }
+
+    /// Torus around the Y axis
+    pub fn torus(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> Self {
+        if major_segments < 3 || minor_segments < 3 {
+            panic!("A torus needs at least 3 segments in each direction!");
+        }
+        let profile = (0..=minor_segments).map(|i| {
+            let angle = -PI + TAU * i as f32 / minor_segments as f32;
+            let (r, y) = (angle.cos(), angle.sin());
+            [major_radius + r * minor_radius, y * minor_radius, r, y, i as f32 / minor_segments as f32]
+        }).collect::<Vec<[f32; 5]>>();
+        let mut shape = lathe(&profile, major_segments);
+        shape.compute_tangents();
+        shape
+    }
+
+    fn push_vertex(&mut self, position: [f32; 3], normal: [f32; 3], uv: [f32; 2]) {
+        self.positions.push(position);
+        self.normals.push(normal);
+        self.uvs.push(uv);
+    }
+}
+
+/// `subdivisions_u * subdivisions_v` quads spanning `u` and `v`, centered on `center`
+fn grid(center: [f32; 3], u: [f32; 3], v: [f32; 3], normal: [f32; 3], subdivisions_u: u32, subdivisions_v: u32) -> MeshData {
+    let mut shape = MeshData::default();
+    for j in 0..=subdivisions_v {
+        for i in 0..=subdivisions_u {
+            let (s, t) = (i as f32 / subdivisions_u as f32, j as f32 / subdivisions_v as f32);
+            shape.push_vertex(add(center, add(scale(u, s - 0.5), scale(v, t - 0.5))), normal, [s, t]);
+        }
+    }
+    let row = subdivisions_u + 1;
+    for j in 0..subdivisions_v {
+        for i in 0..subdivisions_u {
+            let a = j * row + i;
+            shape.indices.extend_from_slice(&[a, a + 1, a + row + 1, a, a + row + 1, a + row]);
+        }
+    }
+    shape
+}
+
+/// Surface of revolution around Y. Each profile point is `[radius, y, normal_radial, normal_y, v]`,
+/// going upwards along the outside of the surface
+fn lathe(profile: &[[f32; 5]], segments: u32) -> MeshData {
+    let mut shape = MeshData::default();
+    for [r, y, nr, ny, v] in profile.iter().copied() {
+        for s in 0..=segments {
+            let u = s as f32 / segments as f32;
+            let (sin, cos) = (u * TAU).sin_cos();
+            shape.push_vertex([r * sin, y, r * cos], normalize([nr * sin, ny, nr * cos]), [u, v]);
+        }
+    }
+    let row = segments + 1;
+    let max_radius = profile.iter().fold(0.0f32, |max, p| max.max(p[0].abs()));
+    let on_axis = |r: f32| r.abs() <= max_radius * 1e-6;
+    for (j, ring) in profile.windows(2).enumerate() {
+        for s in 0..segments {
+            let a = j as u32 * row + s;
+            let (b, c, d) = (a + 1, a + row, a + row + 1);
+            // Skip the triangles that collapse where the profile touches the axis
+            if !on_axis(ring[0][0]) {
+                shape.indices.extend_from_slice(&[a, b, d]);
+            }
+            if !on_axis(ring[1][0]) {
+                shape.indices.extend_from_slice(&[a, d, c]);
+            }
+        }
+    }
+    shape
+}
+
+/// Flat cap at height `y`, facing up or down
+fn disc(radius: f32, y: f32, segments: u32, up: bool) -> MeshData {
+    let ny = if up { 1.0 } else { -1.0 };
+    let mut shape = MeshData::default();
+    shape.push_vertex([0.0, y, 0.0], [0.0, ny, 0.0], [0.5, 0.5]);
+    for s in 0..=segments {
+        let (sin, cos) = (s as f32 / segments as f32 * TAU).sin_cos();
+        shape.push_vertex([radius * sin, y, radius * cos], [0.0, ny, 0.0], [0.5 + sin / 2.0, 0.5 - ny * cos / 2.0]);
+    }
+    for s in 1..=segments {
+        if up {
+            shape.indices.extend_from_slice(&[0, s, s + 1]);
+        } else {
+            shape.indices.extend_from_slice(&[0, s + 1, s]);
+        }
+    }
+    shape
+}
+
+impl Mesh {
+    pub fn from_shape(renderer: &super::Renderer, shape: &Shape) -> Self {
+        Self::from_vertices_indices(renderer, &shape.vertices, &shape.indices)
+    }
+
+    pub fn cube(renderer: &super::Renderer, size: f32) -> Self {
+        Self::from_data(renderer, &MeshData::cube(size))
+    }
+
+    pub fn plane(renderer: &super::Renderer, width: f32, depth: f32, subdivisions_x: u32, subdivisions_z: u32) -> Self {
+        Self::from_data(renderer, &MeshData::plane(width, depth, subdivisions_x, subdivisions_z))
+    }
+
+    pub fn uv_sphere(renderer: &super::Renderer, radius: f32, segments: u32, rings: u32) -> Self {
+        Self::from_data(renderer, &MeshData::uv_sphere(radius, segments, rings))
+    }
+
+    pub fn icosphere(renderer: &super::Renderer, radius: f32, subdivisions: u32) -> Self {
+        Self::from_data(renderer, &MeshData::icosphere(radius, subdivisions))
+    }
+
+    pub fn cylinder(renderer: &super::Renderer, radius: f32, height: f32, segments: u32) -> Self {
+        Self::from_data(renderer, &MeshData::cylinder(radius, height, segments))
+    }
+
+    pub fn cone(renderer: &super::Renderer, radius: f32, height: f32, segments: u32) -> Self {
+        Self::from_data(renderer, &MeshData::cone(radius, height, segments))
+    }
+
+    pub fn capsule(renderer: &super::Renderer, radius: f32, height: f32, segments: u32, rings: u32) -> Self {
+        Self::from_data(renderer, &MeshData::capsule(radius, height, segments, rings))
+    }
+
+    pub fn torus(renderer: &super::Renderer, major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> Self {
+        Self::from_data(renderer, &MeshData::torus(major_radius, minor_radius, major_segments, minor_segments))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::mesh_data::{cross, dot, sub};
+
+    fn all_shapes() -> Vec<(&'static str, Shape)> {
+        vec![
+            ("cube", Shape::cube(2.0)),
+            ("plane", Shape::plane(2.0, 3.0, 4, 2)),
+            ("uv_sphere", Shape::uv_sphere(1.5, 16, 8)),
+            ("icosphere", Shape::icosphere(1.5, 2)),
+            ("cylinder", Shape::cylinder(1.0, 2.0, 12)),
+            ("cone", Shape::cone(1.0, 2.0, 12)),
+            ("capsule", Shape::capsule(0.5, 2.0, 12, 4)),
+            ("torus", Shape::torus(2.0, 0.5, 16, 8)),
+        ]
+    }
+
+    fn assert_outward(name: &str, shape: &Shape, center: impl Fn([f32; 3]) -> [f32; 3]) {
+        for v in &shape.vertices {
+            let outward = dot(v.normal, sub(v.position, center(v.position)));
+            assert!(outward > 0.0, "{} normal {:?} at {:?} points inward", name, v.normal, v.position);
+        }
+    }
+
+    #[test]
+    fn unit_normals() {
+        for (name, shape) in all_shapes() {
+            for v in &shape.vertices {
+                assert!((dot(v.normal, v.normal) - 1.0).abs() < 1e-5, "{} normal {:?} isn't unit length", name, v.normal);
+            }
+        }
+    }
+
+    #[test]
+    fn outward_normals() {
+        assert_outward("uv_sphere", &Shape::uv_sphere(1.5, 16, 8), |_| [0.0; 3]);
+        assert_outward("icosphere", &Shape::icosphere(1.5, 2), |_| [0.0; 3]);
+        // Closest point on the segment between the hemisphere centers
+        assert_outward("capsule", &Shape::capsule(0.5, 2.0, 12, 4), |p| [0.0, p[1].clamp(-1.0, 1.0), 0.0]);
+        // Closest point on the circle through the middle of the tube
+        assert_outward("torus", &Shape::torus(2.0, 0.5, 16, 8), |p| scale(normalize([p[0], 0.0, p[2]]), 2.0));
+    }
+
+    #[test]
+    fn winding_matches_normals() {
+        for (name, shape) in all_shapes() {
+            for triangle in shape.indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| shape.vertices[triangle[i] as usize]);
+                let face = cross(sub(b.position, a.position), sub(c.position, a.position));
+                let normal = add(add(a.normal, b.normal), c.normal);
+                assert!(dot(face, normal) > 0.0, "{} triangle {:?} is wound against its normals", name, triangle);
+            }
+        }
+    }
+
+    #[test]
+    fn uvs_in_range() {
+        for (name, shape) in all_shapes() {
+            for v in &shape.vertices {
+                assert!((0.0..=1.0).contains(&v.uv[1]), "{} v {} is out of range", name, v.uv[1]);
+                if name == "icosphere" && v.uv[0] > 1.0 {
+                    // Seam copies continue past 1 on the triangles that cross the seam at -Z
+                    assert!(v.uv[0] < 1.5 && v.position[2] < 0.0, "{} u {} at {:?} isn't a seam copy", name, v.uv[0], v.position);
+                } else {
+                    assert!((0.0..=1.0).contains(&v.uv[0]), "{} u {} is out of range", name, v.uv[0]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tangents_orthogonal() {
+        for (name, shape) in all_shapes() {
+            for v in &shape.vertices {
+                let t = [v.tangent[0], v.tangent[1], v.tangent[2]];
+                assert!((dot(t, t) - 1.0).abs() < 1e-5, "{} tangent {:?} isn't unit length", name, v.tangent);
+                assert!(dot(t, v.normal).abs() < 1e-4, "{} tangent {:?} isn't orthogonal to {:?}", name, v.tangent, v.normal);
+                assert!(v.tangent[3] == 1.0 || v.tangent[3] == -1.0, "{} tangent w is {}", name, v.tangent[3]);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one subdivision")]
+    fn plane_without_subdivisions() {
+        Shape::plane(1.0, 1.0, 0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 3 segments and 2 rings")]
+    fn sphere_with_one_ring() {
+        Shape::uv_sphere(1.0, 8, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 3 segments")]
+    fn cylinder_with_two_segments() {
+        Shape::cylinder(1.0, 1.0, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 3 segments")]
+    fn cone_with_two_segments() {
+        Shape::cone(1.0, 1.0, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 3 segments and 1 ring")]
+    fn capsule_without_rings() {
+        Shape::capsule(1.0, 1.0, 8, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 3 segments in each direction")]
+    fn torus_with_two_segments() {
+        Shape::torus(2.0, 0.5, 8, 2);
+    }
+}
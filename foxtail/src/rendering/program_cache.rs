@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use glow::*;
+
+use super::gl_ext::GlExt;
+use super::shader::ShaderStage;
+
+/// FNV-1a, so keys stay stable between compiler versions (unlike `DefaultHasher`)
+fn hash_bytes(hash: u64, bytes: &[u8]) -> u64 {
+    let mut hash = hash;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Stores linked program binaries on disk, keyed by the shader sources and the driver.
+/// Enable it with `Renderer::enable_program_cache`.
+pub(crate) struct ProgramCache {
+    dir: PathBuf,
+    driver: String,
+    ext: Arc<GlExt>,
+}
+
+impl ProgramCache {
+    pub(crate) fn new(gl: &Context, ext: Arc<GlExt>, dir: PathBuf) -> Self {
+        let driver = unsafe {
+            format!("{}\n{}\n{}", gl.get_parameter_string(VENDOR), gl.get_parameter_string(RENDERER), gl.get_parameter_string(VERSION))
+        };
+        Self {
+            dir,
+            driver,
+            ext,
+        }
+    }
+
+    pub(crate) fn key(&self, stages: &[(ShaderStage, &str, &str)]) -> u64 {
+        let mut hash = hash_bytes(0xcbf29ce484222325, self.driver.as_bytes());
+        for (stage, src, _) in stages {
+            hash = hash_bytes(hash, &stage.to_gl().to_le_bytes());
+            hash = hash_bytes(hash, &(src.len() as u64).to_le_bytes());
+            hash = hash_bytes(hash, src.as_bytes());
+        }
+        hash
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("{:016x}.bin", key))
+    }
+
+    /// Must be called before linking, otherwise the driver may not keep the binary around
+    pub(crate) unsafe fn prepare(&self, program: NativeProgram) {
+        self.ext.program_parameter_i32(program, PROGRAM_BINARY_RETRIEVABLE_HINT, 1);
+    }
+
+    /// Returns a linked program on a cache hit. Binaries the driver rejects are removed.
+    pub(crate) unsafe fn load(&self, gl: &Context, key: u64) -> Option<NativeProgram> {
+        let path = self.path(key);
+        let bytes = std::fs::read(&path).ok()?;
+        if bytes.len() < 4 {
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+        let format = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+        let program = gl.create_program().expect("Failed to create shader program!");
+        self.prepare(program);
+        self.ext.program_binary(program, format, &bytes[4..]);
+        // Drivers reject binaries after updates or hardware changes, fall back to compiling
+        if !gl.get_program_link_status(program) {
+            debug!("Cached program binary `{}` was rejected by the driver", path.display());
+            gl.delete_program(program);
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+        trace!("Loaded cached program binary `{}`", path.display());
+        Some(program)
+    }
+
+    pub(crate) unsafe fn store(&self, key: u64, program: NativeProgram) {
+        let (format, binary) = match self.ext.get_program_binary(program) {
+            Some(b) => b,
+            None => {
+                warn!("Driver returned no program binary, not caching!");
+                return;
+            }
+        };
+        let mut bytes = Vec::with_capacity(binary.len() + 4);
+        bytes.extend_from_slice(&format.to_le_bytes());
+        bytes.extend_from_slice(&binary);
+
+        let path = self.path(key);
+        if let Err(e) = std::fs::create_dir_all(&self.dir).and_then(|_| std::fs::write(&path, &bytes)) {
+            warn!("Failed to write program binary `{}`: {}", path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use glow::*;
+
+use super::gl_ext::GlExt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageBlockInfo {
+    pub binding: u32,
+    /// Size of the fixed part of the block in bytes.
+    /// A trailing unsized array counts as a single element.
+    pub data_size: usize,
+}
+
+/// Shader storage blocks, atomic counters and images of a linked program, by name
+#[derive(Debug, Clone, Default)]
+pub struct ShaderReflection {
+    pub storage_blocks: HashMap<String, StorageBlockInfo>,
+    /// Binding of the atomic counter buffer each counter lives in
+    pub atomic_counters: HashMap<String, u32>,
+    /// Image unit of each image uniform
+    pub images: HashMap<String, u32>,
+}
+
+fn is_image_type(ty: u32) -> bool {
+    (IMAGE_1D..=UNSIGNED_INT_IMAGE_2D_MULTISAMPLE_ARRAY).contains(&ty)
+}
+
+/// `foo[0]` is how GL names arrays of uniforms
+fn strip_array_suffix(name: String) -> String {
+    match name.strip_suffix("[0]") {
+        Some(stripped) => stripped.to_string(),
+        None => name,
+    }
+}
+
+impl ShaderReflection {
+    pub(crate) fn reflect(gl: &Context, ext: &GlExt, program: NativeProgram) -> Self {
+        let mut reflection = Self::default();
+        if !ext.supports_program_interface_query() {
+            warn!("Program interface queries are not supported, shaders won't be reflected!");
+            return reflection;
+        }
+
+        unsafe {
+            let block_count = ext.get_program_interface_i32(program, SHADER_STORAGE_BLOCK, ACTIVE_RESOURCES);
+            for i in 0..block_count.max(0) as u32 {
+                let name = ext.get_program_resource_name(program, SHADER_STORAGE_BLOCK, i);
+                let props = ext.get_program_resource_i32(program, SHADER_STORAGE_BLOCK, i, &[BUFFER_BINDING, BUFFER_DATA_SIZE]);
+                reflection.storage_blocks.insert(name, StorageBlockInfo {
+                    binding: props[0] as u32,
+                    data_size: props[1] as usize,
+                });
+            }
+
+            let uniform_count = ext.get_program_interface_i32(program, UNIFORM, ACTIVE_RESOURCES);
+            for i in 0..uniform_count.max(0) as u32 {
+                let props = ext.get_program_resource_i32(program, UNIFORM, i, &[TYPE, ATOMIC_COUNTER_BUFFER_INDEX]);
+                let (ty, counter_buffer) = (props[0] as u32, props[1]);
+                if counter_buffer >= 0 {
+                    let name = strip_array_suffix(ext.get_program_resource_name(program, UNIFORM, i));
+                    let binding = ext.get_program_resource_i32(program, ATOMIC_COUNTER_BUFFER, counter_buffer as u32, &[BUFFER_BINDING])[0];
+                    reflection.atomic_counters.insert(name, binding as u32);
+                } else if is_image_type(ty) {
+                    let name = strip_array_suffix(ext.get_program_resource_name(program, UNIFORM, i));
+                    if let Some(loc) = gl.get_uniform_location(program, &name) {
+                        let mut unit = [0i32];
+                        gl.get_uniform_i32(program, &loc, &mut unit);
+                        reflection.images.insert(name, unit[0] as u32);
+                    }
+                }
+            }
+        }
+
+        trace!("Reflected program: {:?}", reflection);
+        reflection
+    }
+}
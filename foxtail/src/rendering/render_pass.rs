@@ -1,12 +1,16 @@
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
 use glow::*;
 
+use super::binding::{BindingGuard, BindingStack};
+use super::resources::{ResourceKind, ResourceRegistry};
+
 pub struct Framebuffer {
     fbo: glow::Framebuffer,
     tex: Vec<glow::Texture>,
     gl: Arc<Context>,
-    shader_bound: Arc<AtomicBool>,
+    bindings: Arc<BindingStack>,
+    state: Arc<super::state::StateCache>,
+    resources: Arc<ResourceRegistry>,
     default_fb_shader: Arc<super::shader::Shader>,
     mesh: super::mesh::Mesh,
     size: (usize, usize),
@@ -14,34 +18,21 @@ pub struct Framebuffer {
 
 impl super::Drawable for Framebuffer {
     fn draw(&self) -> Result<(), super::RenderError> {
-        if self.shader_bound.load(Ordering::Acquire) {
-            for i in 0..self.tex.len() {
-                self.bind_tex(i as u8, i as u32);
-            }
-            self.mesh.draw()?;
-            self.unbind_tex();
+        if self.bindings.program_bound() {
+            let _guards = self.push_textures();
+            self.mesh.draw()
         } else {
             self.default_fb_shader.while_bound(|_| {
-                for i in 0..self.tex.len() {
-                    self.bind_tex(i as u8, i as u32);
-                }
-                self.mesh.draw()?;
-                self.unbind_tex();
-                Ok(())
-            })?;
+                let _guards = self.push_textures();
+                self.mesh.draw()
+            })
         }
-        Ok(())
     }
 }
 
 impl Drop for Framebuffer {
     fn drop(&mut self) {
-        unsafe {
-            self.gl.delete_framebuffer(self.fbo);
-            for tex in &self.tex {
-                self.gl.delete_texture(*tex);
-            }
-        }
+        self.delete_fb();
     }
 }
 
@@ -78,19 +69,51 @@ impl Framebuffer {
         (fbo, tex)
     }
 
+    fn register_fb(&self, label: &str) {
+        let layer_size = self.size.0 * self.size.1 * 16;
+        self.resources.register(ResourceKind::Framebuffer, self.fbo.0.get(), 0, label);
+        for (i, tex) in self.tex.iter().enumerate() {
+            self.resources.register(ResourceKind::Texture, tex.0.get(), layer_size, &format!("{} layer {}", label, i));
+        }
+    }
+
+    fn delete_fb(&self) {
+        self.resources.unregister(ResourceKind::Framebuffer, self.fbo.0.get());
+        unsafe {
+            self.gl.delete_framebuffer(self.fbo);
+            for tex in &self.tex {
+                self.resources.unregister(ResourceKind::Texture, tex.0.get());
+                self.gl.delete_texture(*tex);
+            }
+        }
+    }
+
     pub fn with_resolution(renderer: &super::Renderer, size: (i32, i32), layers: u8) -> Self {
         let gl = renderer.gl.clone();
         let (fbo, tex) = Self::create_fb(gl.clone(), size, layers);
         super::gl_error(&gl, "render_pass::with_resolution");
-        Self {
+        let fb = Self {
             fbo,
             tex,
             gl,
-            shader_bound: renderer.shader_bound.clone(),
+            bindings: renderer.bindings.clone(),
+            state: renderer.state.clone(),
+            resources: renderer.resources.clone(),
             default_fb_shader: renderer.default_fb_shader.clone(),
-            mesh: super::mesh::Mesh::quad(renderer),
+            mesh: super::mesh::Mesh::quad(renderer).with_label("Framebuffer quad"),
             size: (size.0 as usize, size.1 as usize),
+        };
+        fb.register_fb("Framebuffer");
+        fb
+    }
+
+    /// Replaces the label shown in the resource registry and graphics debuggers, layers get `"<label> layer <i>"`
+    pub fn with_label(self, label: &str) -> Self {
+        self.resources.set_label(ResourceKind::Framebuffer, self.fbo.0.get(), label);
+        for (i, tex) in self.tex.iter().enumerate() {
+            self.resources.set_label(ResourceKind::Texture, tex.0.get(), &format!("{} layer {}", label, i));
         }
+        self
     }
 
     pub fn new(renderer: &super::Renderer, layers: u8) -> Self {
@@ -110,17 +133,14 @@ impl Framebuffer {
     }
 
     pub fn resize(&mut self, size: (i32, i32)) {
-        unsafe {
-            self.gl.delete_framebuffer(self.fbo);
-            for tex in &self.tex {
-                self.gl.delete_texture(*tex);
-            }
-        }
+        let label = self.resources.label(ResourceKind::Framebuffer, self.fbo.0.get()).unwrap_or_default();
+        self.delete_fb();
         let (fbo, tex) = Self::create_fb(self.gl.clone(), size, self.tex.len() as u8);
         super::gl_error(&self.gl, "render_pass::resize");
         self.fbo = fbo;
         self.tex = tex;
         self.size = (size.0 as usize, size.1 as usize);
+        self.register_fb(&label);
     }
 
     pub fn size(&self) -> (usize, usize) {
@@ -140,16 +160,9 @@ impl Framebuffer {
         }
     }
 
-    fn bind(&self) {
-        unsafe {
-            self.gl.bind_framebuffer(FRAMEBUFFER, Some(self.fbo));
-        }
-    }
-
-    fn unbind(&self) {
-        unsafe {
-            self.gl.bind_framebuffer(FRAMEBUFFER, None);
-        }
+    /// Binds every layer to the texture unit of the same index
+    fn push_textures(&self) -> Vec<BindingGuard> {
+        self.tex.iter().enumerate().map(|(i, tex)| self.bindings.push_texture(i as u32, TEXTURE_2D, *tex)).collect()
     }
 
     pub fn clear(&self) {
@@ -158,17 +171,16 @@ impl Framebuffer {
         }
     }
 
-    /// Runs a closure while the framebuffer is bound
+    /// Runs a closure while the framebuffer is bound, with the viewport covering it.
+    /// The previously bound framebuffer and its viewport are restored afterwards, so passes can be nested.
     pub fn while_bound<F: FnOnce() -> Result<(), super::RenderError>>(&self, f: F) -> Result<(), super::RenderError> {
-        let mut og_viewport = [0i32; 4];
-        unsafe {
-            self.gl.get_parameter_i32_slice(glow::VIEWPORT, &mut og_viewport);
-            self.gl.viewport(0, 0, self.size.0 as i32, self.size.1 as i32);
-        }
-        self.bind();
-        f()?;
-        self.unbind();
-        unsafe { self.gl.viewport(og_viewport[0], og_viewport[1], og_viewport[2], og_viewport[3]); }
-        Ok(())
+        let _guard = self.bindings.push_framebuffer(Some(self.fbo), [0, 0, self.size.0 as i32, self.size.1 as i32]);
+        f()
+    }
+
+    /// Like `while_bound`, with `state` applied for the pass and the previous state restored afterwards
+    pub fn while_bound_with_state<F: FnOnce() -> Result<(), super::RenderError>>(&self, state: &super::state::RenderState, f: F) -> Result<(), super::RenderError> {
+        let _guard = self.state.push(state);
+        self.while_bound(f)
     }
 }
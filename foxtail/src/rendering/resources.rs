@@ -0,0 +1,189 @@
+//! Bookkeeping for every GL object foxtail creates, to find leaks and see where GPU memory goes.
+//! Query it with `Renderer::resources`, show it with `ResourceRegistry::window`.
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use glow::*;
+
+use foxtail_ui::egui;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ResourceKind {
+    Buffer,
+    Texture,
+    Program,
+    VertexArray,
+    Framebuffer,
+    Sampler,
+}
+
+impl ResourceKind {
+    pub const ALL: [Self; 6] = [Self::Buffer, Self::Texture, Self::Program, Self::VertexArray, Self::Framebuffer, Self::Sampler];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Buffer => "Buffer",
+            Self::Texture => "Texture",
+            Self::Program => "Program",
+            Self::VertexArray => "Vertex array",
+            Self::Framebuffer => "Framebuffer",
+            Self::Sampler => "Sampler",
+        }
+    }
+
+    fn to_gl(self) -> u32 {
+        match self {
+            Self::Buffer => BUFFER,
+            Self::Texture => TEXTURE,
+            Self::Program => PROGRAM,
+            Self::VertexArray => VERTEX_ARRAY,
+            Self::Framebuffer => FRAMEBUFFER,
+            Self::Sampler => SAMPLER,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceInfo {
+    pub kind: ResourceKind,
+    /// The GL name of the object
+    pub id: u32,
+    /// Estimated GPU memory, 0 where it's unknown (programs, vertex arrays, framebuffers, samplers)
+    pub bytes: usize,
+    /// What created it, also set as the GL object label for graphics debuggers
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceStats {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+pub struct ResourceRegistry {
+    gl: Arc<Context>,
+    live: Mutex<BTreeMap<(ResourceKind, u32), ResourceInfo>>,
+}
+
+impl ResourceRegistry {
+    pub(crate) fn new(gl: Arc<Context>) -> Self {
+        Self {
+            gl,
+            live: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn live(&self) -> std::sync::MutexGuard<'_, BTreeMap<(ResourceKind, u32), ResourceInfo>> {
+        self.live.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn register(&self, kind: ResourceKind, id: u32, bytes: usize, label: &str) {
+        unsafe { self.gl.object_label(kind.to_gl(), id, Some(label)); }
+        self.live().insert((kind, id), ResourceInfo { kind, id, bytes, label: label.to_string() });
+    }
+
+    pub(crate) fn set_bytes(&self, kind: ResourceKind, id: u32, bytes: usize) {
+        if let Some(info) = self.live().get_mut(&(kind, id)) {
+            info.bytes = bytes;
+        }
+    }
+
+    pub(crate) fn set_label(&self, kind: ResourceKind, id: u32, label: &str) {
+        unsafe { self.gl.object_label(kind.to_gl(), id, Some(label)); }
+        if let Some(info) = self.live().get_mut(&(kind, id)) {
+            info.label = label.to_string();
+        }
+    }
+
+    pub(crate) fn label(&self, kind: ResourceKind, id: u32) -> Option<String> {
+        self.live().get(&(kind, id)).map(|info| info.label.clone())
+    }
+
+    /// For objects that get recreated (like a resized texture), keeps the label of the old one
+    pub(crate) fn replace(&self, kind: ResourceKind, old_id: u32, new_id: u32, bytes: usize) {
+        let label = self.label(kind, old_id).unwrap_or_default();
+        self.unregister(kind, old_id);
+        self.register(kind, new_id, bytes, &label);
+    }
+
+    pub(crate) fn unregister(&self, kind: ResourceKind, id: u32) {
+        self.live().remove(&(kind, id));
+    }
+
+    /// Every live resource, sorted by kind and GL name
+    pub fn resources(&self) -> Vec<ResourceInfo> {
+        self.live().values().cloned().collect()
+    }
+
+    pub fn stats(&self, kind: ResourceKind) -> ResourceStats {
+        self.live().values().filter(|info| info.kind == kind).fold(ResourceStats::default(), |stats, info| ResourceStats {
+            count: stats.count + 1,
+            bytes: stats.bytes + info.bytes,
+        })
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.live().values().map(|info| info.bytes).sum()
+    }
+
+    /// Lists every resource that is still alive, or `None` if there are none.
+    /// The renderer logs this when it is dropped.
+    pub fn leak_report(&self) -> Option<String> {
+        let live = self.live();
+        if live.is_empty() {
+            return None;
+        }
+        let total = live.values().map(|info| info.bytes).sum();
+        let mut report = format!("{} GPU resources ({}) were never deleted:", live.len(), format_bytes(total));
+        for info in live.values() {
+            report.push_str(&format!("\n  {} #{}, {}: {}", info.kind.name(), info.id, format_bytes(info.bytes), info.label));
+        }
+        Some(report)
+    }
+
+    /// Summary per kind, with every live resource in a collapsible list below
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        egui::Grid::new("foxtail_resource_stats").striped(true).show(ui, |ui| {
+            ui.strong("Kind");
+            ui.strong("Live");
+            ui.strong("Memory");
+            ui.end_row();
+            for kind in ResourceKind::ALL {
+                let stats = self.stats(kind);
+                ui.label(kind.name());
+                ui.label(stats.count.to_string());
+                ui.label(format_bytes(stats.bytes));
+                ui.end_row();
+            }
+            ui.strong("Total");
+            ui.strong(self.live().len().to_string());
+            ui.strong(format_bytes(self.total_bytes()));
+            ui.end_row();
+        });
+        ui.collapsing("All resources", |ui| {
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                egui::Grid::new("foxtail_resource_list").striped(true).show(ui, |ui| {
+                    for info in self.resources() {
+                        ui.label(format!("{} #{}", info.kind.name(), info.id));
+                        ui.label(format_bytes(info.bytes));
+                        ui.label(info.label);
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+    }
+
+    /// Debug panel with `ui` in its own window, call it from `Context::draw_ui`
+    pub fn window(&self, ctx: &egui::Context) {
+        egui::Window::new("GPU resources").show(ctx, |ui| self.ui(ui));
+    }
+}
+
+fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b >= 1 << 30 => format!("{:.2} GiB", b as f64 / (1u64 << 30) as f64),
+        b if b >= 1 << 20 => format!("{:.2} MiB", b as f64 / (1u64 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.2} KiB", b as f64 / (1u64 << 10) as f64),
+        b => format!("{} B", b),
+    }
+}
@@ -0,0 +1,186 @@
+//! Sampler objects, describing how textures are filtered and wrapped independently of the textures themselves.
+//! A sampler bound to a texture unit overrides the sampling settings of whatever texture is bound there.
+use std::sync::Arc;
+use glow::*;
+
+use super::binding::BindingStack;
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::state::CompareFunc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapMode {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    /// Samples outside the texture return `SamplerSettings::border_color`
+    ClampToBorder,
+}
+
+impl WrapMode {
+    fn to_gl(self) -> i32 {
+        (match self {
+            Self::Repeat => REPEAT,
+            Self::MirroredRepeat => MIRRORED_REPEAT,
+            Self::ClampToEdge => CLAMP_TO_EDGE,
+            Self::ClampToBorder => CLAMP_TO_BORDER,
+        }) as i32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+impl Filter {
+    fn to_gl(self) -> i32 {
+        (match self {
+            Self::Nearest => NEAREST,
+            Self::Linear => LINEAR,
+        }) as i32
+    }
+
+    fn to_gl_min(self, mipmap: Option<Self>) -> i32 {
+        (match (self, mipmap) {
+            (_, None) => return self.to_gl(),
+            (Self::Nearest, Some(Self::Nearest)) => NEAREST_MIPMAP_NEAREST,
+            (Self::Linear, Some(Self::Nearest)) => LINEAR_MIPMAP_NEAREST,
+            (Self::Nearest, Some(Self::Linear)) => NEAREST_MIPMAP_LINEAR,
+            (Self::Linear, Some(Self::Linear)) => LINEAR_MIPMAP_LINEAR,
+        }) as i32
+    }
+}
+
+/// The default matches GL's defaults, except for linear filtering between mip levels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerSettings {
+    /// S, T and R
+    pub wrap: [WrapMode; 3],
+    pub border_color: [f32; 4],
+    pub min_filter: Filter,
+    pub mag_filter: Filter,
+    /// How mip levels are blended, `None` only samples the base level
+    pub mipmap_filter: Option<Filter>,
+    pub min_lod: f32,
+    pub max_lod: f32,
+    pub lod_bias: f32,
+    /// 1 disables anisotropic filtering, higher values are clamped to what the driver supports
+    pub max_anisotropy: f32,
+    /// For depth textures, compares against the reference coordinate instead of returning depth (`sampler2DShadow`)
+    pub compare: Option<CompareFunc>,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        Self {
+            wrap: [WrapMode::Repeat; 3],
+            border_color: [0.0; 4],
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mipmap_filter: Some(Filter::Linear),
+            min_lod: -1000.0,
+            max_lod: 1000.0,
+            lod_bias: 0.0,
+            max_anisotropy: 1.0,
+            compare: None,
+        }
+    }
+}
+
+impl SamplerSettings {
+    /// Blocky pixel art, no mipmaps
+    pub fn nearest() -> Self {
+        Self {
+            min_filter: Filter::Nearest,
+            mag_filter: Filter::Nearest,
+            mipmap_filter: None,
+            ..Default::default()
+        }
+    }
+
+    /// Hardware PCF for shadow maps, everything outside the map is lit
+    pub fn shadow() -> Self {
+        Self {
+            wrap: [WrapMode::ClampToBorder; 3],
+            border_color: [1.0; 4],
+            mipmap_filter: None,
+            compare: Some(CompareFunc::LessEqual),
+            ..Default::default()
+        }
+    }
+}
+
+pub struct Sampler {
+    sampler: NativeSampler,
+    settings: SamplerSettings,
+    gl: Arc<Context>,
+    bindings: Arc<BindingStack>,
+    resources: Arc<ResourceRegistry>,
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        self.resources.unregister(ResourceKind::Sampler, self.sampler.0.get());
+        unsafe {
+            self.gl.delete_sampler(self.sampler);
+        }
+    }
+}
+
+impl Sampler {
+    pub fn new(renderer: &super::Renderer, settings: SamplerSettings) -> Self {
+        let gl = renderer.gl.clone();
+        let sampler = unsafe {
+            let sampler = gl.create_sampler().map_err(|e| error!("{}", e)).expect("Failed to create sampler!");
+            gl.sampler_parameter_i32(sampler, TEXTURE_WRAP_S, settings.wrap[0].to_gl());
+            gl.sampler_parameter_i32(sampler, TEXTURE_WRAP_T, settings.wrap[1].to_gl());
+            gl.sampler_parameter_i32(sampler, TEXTURE_WRAP_R, settings.wrap[2].to_gl());
+            gl.sampler_parameter_f32_slice(sampler, TEXTURE_BORDER_COLOR, &settings.border_color);
+            gl.sampler_parameter_i32(sampler, TEXTURE_MIN_FILTER, settings.min_filter.to_gl_min(settings.mipmap_filter));
+            gl.sampler_parameter_i32(sampler, TEXTURE_MAG_FILTER, settings.mag_filter.to_gl());
+            gl.sampler_parameter_f32(sampler, TEXTURE_MIN_LOD, settings.min_lod);
+            gl.sampler_parameter_f32(sampler, TEXTURE_MAX_LOD, settings.max_lod);
+            gl.sampler_parameter_f32(sampler, TEXTURE_LOD_BIAS, settings.lod_bias);
+            let max_anisotropy = gl.get_parameter_f32(MAX_TEXTURE_MAX_ANISOTROPY);
+            gl.sampler_parameter_f32(sampler, TEXTURE_MAX_ANISOTROPY, settings.max_anisotropy.clamp(1.0, max_anisotropy.max(1.0)));
+            match settings.compare {
+                Some(func) => {
+                    gl.sampler_parameter_i32(sampler, TEXTURE_COMPARE_MODE, COMPARE_REF_TO_TEXTURE as i32);
+                    gl.sampler_parameter_i32(sampler, TEXTURE_COMPARE_FUNC, func.to_gl() as i32);
+                },
+                None => gl.sampler_parameter_i32(sampler, TEXTURE_COMPARE_MODE, NONE as i32),
+            }
+            sampler
+        };
+        super::gl_error(&gl, "sampler::new");
+        renderer.resources.register(ResourceKind::Sampler, sampler.0.get(), 0, "Sampler");
+        Self {
+            sampler,
+            settings,
+            gl,
+            bindings: renderer.bindings.clone(),
+            resources: renderer.resources.clone(),
+        }
+    }
+
+    /// Replaces the label shown in the resource registry and graphics debuggers
+    pub fn with_label(self, label: &str) -> Self {
+        self.resources.set_label(ResourceKind::Sampler, self.sampler.0.get(), label);
+        self
+    }
+
+    pub fn settings(&self) -> &SamplerSettings {
+        &self.settings
+    }
+
+    /// Runs a closure while the sampler is bound to texture unit `location`, see `Texture::while_bound_with`.
+    /// Whatever sampler was bound to that unit before is bound again afterwards.
+    pub fn while_bound<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, f: F) -> Result<(), super::RenderError> {
+        if !self.bindings.program_bound() {
+            panic!("No shader bound, but you are trying to bind a sampler!");
+        }
+        let _guard = self.bindings.push_sampler(location, self.sampler);
+        f()
+    }
+}
@@ -0,0 +1,597 @@
+//! glTF 2.0 import (`.gltf` with external or embedded buffers and images, and `.glb`).
+//! Every primitive becomes a `Mesh` with separate streams for the attributes it has:
+//!
+//! | Attribute    | Location | Type               |
+//! |--------------|----------|--------------------|
+//! | `POSITION`   | 0        | `vec3`             |
+//! | `NORMAL`     | 1        | `vec3`             |
+//! | `TEXCOORD_0` | 2        | `vec2`             |
+//! | `TANGENT`    | 3        | `vec4`             |
+//! | `TEXCOORD_1` | 4        | `vec2`             |
+//! | `COLOR_0`    | 5        | `vec4`             |
+//! | `JOINTS_0`   | 6        | `uvec4` (integer)  |
+//! | `WEIGHTS_0`  | 7        | `vec4`             |
+//!
+//! The vertex data stays available on the CPU in `Primitive::data`.
+use std::path::Path;
+
+use super::mesh::{Indices, Mesh, Topology};
+use super::sampler::{Filter, Sampler, SamplerSettings, WrapMode};
+use super::texture::{Texture, TextureFiltering, TextureFormat, TextureSettings};
+use super::vertex::{slice_as_bytes, AttribType, VertexLayout, VertexStream};
+
+pub use gltf::Error as GltfError;
+
+/// CPU side copy of a primitive's vertices, empty where the attribute is missing
+#[derive(Debug, Clone, Default)]
+pub struct PrimitiveData {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub tangents: Vec<[f32; 4]>,
+    pub uvs0: Vec<[f32; 2]>,
+    pub uvs1: Vec<[f32; 2]>,
+    pub colors: Vec<[f32; 4]>,
+    pub joints: Vec<[u16; 4]>,
+    pub weights: Vec<[f32; 4]>,
+    /// Empty for non-indexed primitives
+    pub indices: Vec<u32>,
+    /// Axis aligned bounds of the positions
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+pub struct Primitive {
+    pub mesh: Mesh,
+    pub data: PrimitiveData,
+    /// Index into `GltfScene::materials`
+    pub material: Option<usize>,
+}
+
+pub struct SceneMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<Primitive>,
+}
+
+/// A texture used by a material
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureRef {
+    /// Index into `GltfScene::textures`
+    pub texture: usize,
+    /// Index into `GltfScene::samplers`, bind it with `Texture::while_bound_with`
+    pub sampler: usize,
+    /// Which `TEXCOORD_n` set to sample with
+    pub tex_coord: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    /// Fragments with an alpha below the cutoff are discarded
+    Mask(f32),
+    Blend,
+}
+
+/// Metallic-roughness PBR parameters, as in the glTF spec
+#[derive(Debug, Clone, PartialEq)]
+pub struct PbrMaterial {
+    pub name: Option<String>,
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<TextureRef>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    /// Roughness in green, metallic in blue
+    pub metallic_roughness_texture: Option<TextureRef>,
+    pub normal_texture: Option<TextureRef>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<TextureRef>,
+    pub occlusion_strength: f32,
+    pub emissive_factor: [f32; 3],
+    pub emissive_texture: Option<TextureRef>,
+    pub alpha_mode: AlphaMode,
+    pub double_sided: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: [f32; 3],
+    /// Quaternion as `[x, y, z, w]`
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+
+impl Transform {
+    /// Column-major 4x4 matrix
+    pub fn matrix(&self) -> [f32; 16] {
+        let [x, y, z, w] = self.rotation;
+        let [sx, sy, sz] = self.scale;
+        let [tx, ty, tz] = self.translation;
+        [
+            (1.0 - 2.0 * (y * y + z * z)) * sx, (2.0 * (x * y + z * w)) * sx, (2.0 * (x * z - y * w)) * sx, 0.0,
+            (2.0 * (x * y - z * w)) * sy, (1.0 - 2.0 * (x * x + z * z)) * sy, (2.0 * (y * z + x * w)) * sy, 0.0,
+            (2.0 * (x * z + y * w)) * sz, (2.0 * (y * z - x * w)) * sz, (1.0 - 2.0 * (x * x + y * y)) * sz, 0.0,
+            tx, ty, tz, 1.0,
+        ]
+    }
+}
+
+/// Multiplies two column-major 4x4 matrices
+fn mul(a: &[f32; 16], b: &[f32; 16]) -> [f32; 16] {
+    let mut out = [0.0; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            out[col * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+        }
+    }
+    out
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub name: Option<String>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub transform: Transform,
+    /// Index into `GltfScene::meshes`
+    pub mesh: Option<usize>,
+    /// Index into `GltfScene::skins`
+    pub skin: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skin {
+    pub name: Option<String>,
+    /// Node indices, in the order `JOINTS_0` refers to them
+    pub joints: Vec<usize>,
+    /// Column-major, one per joint
+    pub inverse_bind_matrices: Vec<[f32; 16]>,
+    pub skeleton: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationProperty {
+    Translation,
+    Rotation,
+    Scale,
+    MorphTargetWeights,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    Step,
+    /// Every keyframe has an in-tangent, a value and an out-tangent
+    CubicSpline,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationChannel {
+    pub node: usize,
+    pub property: AnimationProperty,
+    pub interpolation: Interpolation,
+    /// Keyframe times in seconds
+    pub times: Vec<f32>,
+    /// Flattened keyframe values, 3 floats each for translation and scale, 4 for rotation
+    pub values: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub name: Option<String>,
+    pub channels: Vec<AnimationChannel>,
+}
+
+pub struct GltfScene {
+    pub meshes: Vec<SceneMesh>,
+    pub materials: Vec<PbrMaterial>,
+    /// One per glTF texture
+    pub textures: Vec<Texture>,
+    /// One per glTF sampler, followed by the default one for textures without a sampler
+    pub samplers: Vec<Sampler>,
+    pub nodes: Vec<Node>,
+    /// Root nodes of the default scene, a file can have other parentless nodes outside it
+    pub roots: Vec<usize>,
+    pub skins: Vec<Skin>,
+    pub animations: Vec<Animation>,
+}
+
+impl GltfScene {
+    /// Loads a `.gltf` or `.glb` file, along with external buffers and images next to it
+    pub fn load<P: AsRef<Path>>(renderer: &super::Renderer, path: P) -> Result<Self, GltfError> {
+        let (document, buffers, images) = gltf::import(path)?;
+        Ok(Self::from_import(renderer, &document, &buffers, &images))
+    }
+
+    /// Loads a `.glb` or a `.gltf` with only embedded data from memory
+    pub fn from_slice(renderer: &super::Renderer, data: &[u8]) -> Result<Self, GltfError> {
+        let (document, buffers, images) = gltf::import_slice(data)?;
+        Ok(Self::from_import(renderer, &document, &buffers, &images))
+    }
+
+    fn from_import(renderer: &super::Renderer, document: &gltf::Document, buffers: &[gltf::buffer::Data], images: &[gltf::image::Data]) -> Self {
+        // Base color and emissive textures hold sRGB colors, everything else is linear data
+        let srgb = document.materials().flat_map(|m| [m.pbr_metallic_roughness().base_color_texture(), m.emissive_texture()])
+            .flatten()
+            .map(|info| info.texture().index())
+            .collect::<std::collections::HashSet<usize>>();
+        let textures = document.textures().map(|t| {
+            let filtering = match t.sampler().mag_filter() {
+                Some(gltf::texture::MagFilter::Nearest) => TextureFiltering::Nearest,
+                _ => TextureFiltering::Linear,
+            };
+            load_image(renderer, &images[t.source().index()], filtering, srgb.contains(&t.index()))
+        }).collect();
+
+        let meshes = document.meshes().map(|m| SceneMesh {
+            name: m.name().map(|s| s.to_string()),
+            primitives: m.primitives().map(|p| load_primitive(renderer, &p, buffers)).collect(),
+        }).collect();
+
+        let mut samplers = document.samplers().map(|s| {
+            let sampler = Sampler::new(renderer, sampler_settings(&s));
+            match s.name() {
+                Some(name) => sampler.with_label(name),
+                None => sampler,
+            }
+        }).collect::<Vec<Sampler>>();
+        let default_sampler = samplers.len();
+        samplers.push(Sampler::new(renderer, SamplerSettings::default()));
+
+        let materials = document.materials().map(|m| load_material(&m, default_sampler)).collect();
+
+        let mut nodes = document.nodes().map(|n| {
+            let (translation, rotation, scale) = n.transform().decomposed();
+            Node {
+                name: n.name().map(|s| s.to_string()),
+                parent: None,
+                children: n.children().map(|c| c.index()).collect(),
+                transform: Transform { translation, rotation, scale },
+                mesh: n.mesh().map(|m| m.index()),
+                skin: n.skin().map(|s| s.index()),
+            }
+        }).collect::<Vec<Node>>();
+        for i in 0..nodes.len() {
+            for child in nodes[i].children.clone() {
+                nodes[child].parent = Some(i);
+            }
+        }
+
+        let roots = match document.default_scene().or_else(|| document.scenes().next()) {
+            Some(scene) => scene.nodes().map(|n| n.index()).collect(),
+            None => (0..nodes.len()).filter(|i| nodes[*i].parent.is_none()).collect(),
+        };
+
+        let skins = document.skins().map(|s| {
+            let reader = s.reader(|b| Some(&buffers[b.index()]));
+            let joints = s.joints().map(|j| j.index()).collect::<Vec<usize>>();
+            let inverse_bind_matrices = match reader.read_inverse_bind_matrices() {
+                Some(matrices) => matrices.map(flatten).collect(),
+                None => vec![Transform { translation: [0.0; 3], rotation: [0.0, 0.0, 0.0, 1.0], scale: [1.0; 3] }.matrix(); joints.len()],
+            };
+            Skin {
+                name: s.name().map(|s| s.to_string()),
+                joints,
+                inverse_bind_matrices,
+                skeleton: s.skeleton().map(|n| n.index()),
+            }
+        }).collect();
+
+        let animations = document.animations().map(|a| Animation {
+            name: a.name().map(|s| s.to_string()),
+            channels: a.channels().filter_map(|c| load_channel(&c, buffers)).collect(),
+        }).collect();
+
+        Self { meshes, materials, textures, samplers, nodes, roots, skins, animations }
+    }
+
+    /// Model matrix of every node, with the transforms of its parents applied.
+    /// Covers every node, including the ones outside the default scene.
+    pub fn world_matrices(&self) -> Vec<[f32; 16]> {
+        let mut world = vec![[0.0; 16]; self.nodes.len()];
+        let mut stack = (0..self.nodes.len()).filter(|n| self.nodes[*n].parent.is_none()).map(|n| (n, None)).collect::<Vec<(usize, Option<usize>)>>();
+        while let Some((node, parent)) = stack.pop() {
+            let local = self.nodes[node].transform.matrix();
+            world[node] = match parent {
+                Some(p) => mul(&world[p], &local),
+                None => local,
+            };
+            stack.extend(self.nodes[node].children.iter().map(|c| (*c, Some(node))));
+        }
+        world
+    }
+}
+
+fn flatten(m: [[f32; 4]; 4]) -> [f32; 16] {
+    let mut out = [0.0; 16];
+    for (i, col) in m.iter().enumerate() {
+        out[i * 4..i * 4 + 4].copy_from_slice(col);
+    }
+    out
+}
+
+/// 8-bit and float images are uploaded as they are, 16-bit ones are converted to `RGBA16F`.
+/// Color textures (`srgb`) get an sRGB format.
+fn load_image(renderer: &super::Renderer, image: &gltf::image::Data, filtering: TextureFiltering, srgb: bool) -> Texture {
+    use gltf::image::Format;
+    let mut converted = Vec::new();
+    let format = match image.format {
+        Format::R8 => TextureFormat::R8,
+        Format::R8G8 => TextureFormat::RG8,
+        Format::R8G8B8 => if srgb { TextureFormat::SRGB8 } else { TextureFormat::RGB8 },
+        Format::R8G8B8A8 => if srgb { TextureFormat::SRGB8A8 } else { TextureFormat::RGBA8 },
+        Format::R32G32B32FLOAT => TextureFormat::RGB32F,
+        Format::R32G32B32A32FLOAT => TextureFormat::RGBA32F,
+        Format::R16 | Format::R16G16 | Format::R16G16B16 | Format::R16G16B16A16 => {
+            let channels = match image.format {
+                Format::R16 => 1,
+                Format::R16G16 => 2,
+                Format::R16G16B16 => 3,
+                _ => 4,
+            };
+            for pixel in image.pixels.chunks_exact(channels * 2) {
+                for c in 0..4 {
+                    converted.push(if c < channels {
+                        u16::from_le_bytes([pixel[c * 2], pixel[c * 2 + 1]]) as f32 / 65535.0
+                    } else if c == 3 {
+                        1.0
+                    } else {
+                        0.0
+                    });
+                }
+            }
+            TextureFormat::RGBA16F
+        },
+    };
+    let settings = TextureSettings {
+        width: image.width as usize,
+        height: image.height as usize,
+        format,
+        filtering,
+        mipmap: true,
+    };
+    let pixels = if converted.is_empty() { &image.pixels[..] } else { slice_as_bytes(&converted) };
+    Texture::new(renderer, settings, Some(pixels))
+}
+
+/// glTF doesn't specify filters when they're missing, those use linear filtering between mip levels
+fn sampler_settings(s: &gltf::texture::Sampler) -> SamplerSettings {
+    use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+    let wrap = |mode| match mode {
+        WrappingMode::ClampToEdge => WrapMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => WrapMode::MirroredRepeat,
+        WrappingMode::Repeat => WrapMode::Repeat,
+    };
+    let (min_filter, mipmap_filter) = match s.min_filter() {
+        Some(MinFilter::Nearest) => (Filter::Nearest, None),
+        Some(MinFilter::Linear) => (Filter::Linear, None),
+        Some(MinFilter::NearestMipmapNearest) => (Filter::Nearest, Some(Filter::Nearest)),
+        Some(MinFilter::LinearMipmapNearest) => (Filter::Linear, Some(Filter::Nearest)),
+        Some(MinFilter::NearestMipmapLinear) => (Filter::Nearest, Some(Filter::Linear)),
+        Some(MinFilter::LinearMipmapLinear) | None => (Filter::Linear, Some(Filter::Linear)),
+    };
+    SamplerSettings {
+        wrap: [wrap(s.wrap_s()), wrap(s.wrap_t()), WrapMode::Repeat],
+        min_filter,
+        mag_filter: match s.mag_filter() {
+            Some(MagFilter::Nearest) => Filter::Nearest,
+            _ => Filter::Linear,
+        },
+        mipmap_filter,
+        ..Default::default()
+    }
+}
+
+fn texture_ref(texture: gltf::Texture, tex_coord: u32, default_sampler: usize) -> TextureRef {
+    TextureRef {
+        texture: texture.index(),
+        sampler: texture.sampler().index().unwrap_or(default_sampler),
+        tex_coord,
+    }
+}
+
+fn load_material(m: &gltf::Material, default_sampler: usize) -> PbrMaterial {
+    let pbr = m.pbr_metallic_roughness();
+    let normal = m.normal_texture();
+    let occlusion = m.occlusion_texture();
+    PbrMaterial {
+        name: m.name().map(|s| s.to_string()),
+        base_color_factor: pbr.base_color_factor(),
+        base_color_texture: pbr.base_color_texture().map(|i| texture_ref(i.texture(), i.tex_coord(), default_sampler)),
+        metallic_factor: pbr.metallic_factor(),
+        roughness_factor: pbr.roughness_factor(),
+        metallic_roughness_texture: pbr.metallic_roughness_texture().map(|i| texture_ref(i.texture(), i.tex_coord(), default_sampler)),
+        normal_texture: normal.as_ref().map(|n| texture_ref(n.texture(), n.tex_coord(), default_sampler)),
+        normal_scale: normal.as_ref().map_or(1.0, |n| n.scale()),
+        occlusion_texture: occlusion.as_ref().map(|o| texture_ref(o.texture(), o.tex_coord(), default_sampler)),
+        occlusion_strength: occlusion.as_ref().map_or(1.0, |o| o.strength()),
+        emissive_factor: m.emissive_factor(),
+        emissive_texture: m.emissive_texture().map(|i| texture_ref(i.texture(), i.tex_coord(), default_sampler)),
+        alpha_mode: match m.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => AlphaMode::Mask(m.alpha_cutoff().unwrap_or(0.5)),
+            gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        },
+        double_sided: m.double_sided(),
+    }
+}
+
+fn load_primitive(renderer: &super::Renderer, p: &gltf::Primitive, buffers: &[gltf::buffer::Data]) -> Primitive {
+    let reader = p.reader(|b| Some(&buffers[b.index()]));
+    let bounds = p.bounding_box();
+    let data = PrimitiveData {
+        positions: reader.read_positions().map_or(Vec::new(), |i| i.collect()),
+        normals: reader.read_normals().map_or(Vec::new(), |i| i.collect()),
+        tangents: reader.read_tangents().map_or(Vec::new(), |i| i.collect()),
+        uvs0: reader.read_tex_coords(0).map_or(Vec::new(), |i| i.into_f32().collect()),
+        uvs1: reader.read_tex_coords(1).map_or(Vec::new(), |i| i.into_f32().collect()),
+        colors: reader.read_colors(0).map_or(Vec::new(), |i| i.into_rgba_f32().collect()),
+        joints: reader.read_joints(0).map_or(Vec::new(), |i| i.into_u16().collect()),
+        weights: reader.read_weights(0).map_or(Vec::new(), |i| i.into_f32().collect()),
+        indices: reader.read_indices().map_or(Vec::new(), |i| i.into_u32().collect()),
+        min: bounds.min,
+        max: bounds.max,
+    };
+
+    let attributes = [
+        (0, AttribType::F32, 3, false, 12, slice_as_bytes(&data.positions)),
+        (1, AttribType::F32, 3, false, 12, slice_as_bytes(&data.normals)),
+        (2, AttribType::F32, 2, false, 8, slice_as_bytes(&data.uvs0)),
+        (3, AttribType::F32, 4, false, 16, slice_as_bytes(&data.tangents)),
+        (4, AttribType::F32, 2, false, 8, slice_as_bytes(&data.uvs1)),
+        (5, AttribType::F32, 4, false, 16, slice_as_bytes(&data.colors)),
+        (6, AttribType::U16, 4, true, 8, slice_as_bytes(&data.joints)),
+        (7, AttribType::F32, 4, false, 16, slice_as_bytes(&data.weights)),
+    ];
+    let mut streams = Vec::new();
+    let mut bytes = Vec::new();
+    for (location, ty, components, integer, stride, raw) in attributes {
+        if raw.is_empty() {
+            continue;
+        }
+        let stream = VertexStream::new(stride);
+        streams.push(if integer { stream.integer(location, ty, components, 0) } else { stream.float(location, ty, components, 0) });
+        bytes.push(raw);
+    }
+
+    let indices = if data.indices.is_empty() { Indices::None } else { Indices::U32(&data.indices) };
+    let topology = match p.mode() {
+        gltf::mesh::Mode::Points => Topology::Points,
+        gltf::mesh::Mode::Lines => Topology::Lines,
+        gltf::mesh::Mode::LineLoop => Topology::LineLoop,
+        gltf::mesh::Mode::LineStrip => Topology::LineStrip,
+        gltf::mesh::Mode::Triangles => Topology::Triangles,
+        gltf::mesh::Mode::TriangleStrip => Topology::TriangleStrip,
+        gltf::mesh::Mode::TriangleFan => Topology::TriangleFan,
+    };
+    let mesh = Mesh::from_layout(renderer, &VertexLayout::separate(streams), &bytes, indices).with_topology(topology);
+
+    Primitive {
+        mesh,
+        data,
+        material: p.material().index(),
+    }
+}
+
+fn load_channel(c: &gltf::animation::Channel, buffers: &[gltf::buffer::Data]) -> Option<AnimationChannel> {
+    use gltf::animation::util::ReadOutputs;
+    let reader = c.reader(|b| Some(&buffers[b.index()]));
+    let times = reader.read_inputs()?.collect();
+    let (property, values) = match reader.read_outputs()? {
+        ReadOutputs::Translations(t) => (AnimationProperty::Translation, t.flatten().collect()),
+        ReadOutputs::Rotations(r) => (AnimationProperty::Rotation, r.into_f32().flatten().collect()),
+        ReadOutputs::Scales(s) => (AnimationProperty::Scale, s.flatten().collect()),
+        ReadOutputs::MorphTargetWeights(w) => (AnimationProperty::MorphTargetWeights, w.into_f32().collect()),
+    };
+    Some(AnimationChannel {
+        node: c.target().node().index(),
+        property,
+        interpolation: match c.sampler().interpolation() {
+            gltf::animation::Interpolation::Linear => Interpolation::Linear,
+            gltf::animation::Interpolation::Step => Interpolation::Step,
+            gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+        },
+        times,
+        values,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDENTITY: [f32; 16] = [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+
+    fn assert_close(a: &[f32; 16], b: &[f32; 16]) {
+        assert!(a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-6), "{:?} != {:?}", a, b);
+    }
+
+    fn transform(translation: [f32; 3], rotation: [f32; 4], scale: [f32; 3]) -> Transform {
+        Transform { translation, rotation, scale }
+    }
+
+    fn node(parent: Option<usize>, children: Vec<usize>, transform: Transform) -> Node {
+        Node { name: None, parent, children, transform, mesh: None, skin: None }
+    }
+
+    #[test]
+    fn transform_matrix() {
+        assert_close(&transform([0.0; 3], [0.0, 0.0, 0.0, 1.0], [1.0; 3]).matrix(), &IDENTITY);
+        assert_close(&transform([1.0, 2.0, 3.0], [0.0, 0.0, 0.0, 1.0], [2.0, 3.0, 4.0]).matrix(), &[
+            2.0, 0.0, 0.0, 0.0,
+            0.0, 3.0, 0.0, 0.0,
+            0.0, 0.0, 4.0, 0.0,
+            1.0, 2.0, 3.0, 1.0,
+        ]);
+        // 90 degrees around Z turns X into Y and Y into -X
+        let s = std::f32::consts::FRAC_1_SQRT_2;
+        assert_close(&transform([5.0, 0.0, 0.0], [0.0, 0.0, s, s], [1.0; 3]).matrix(), &[
+            0.0, 1.0, 0.0, 0.0,
+            -1.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            5.0, 0.0, 0.0, 1.0,
+        ]);
+    }
+
+    #[test]
+    fn matrix_mul() {
+        let translate = transform([1.0, 2.0, 3.0], [0.0, 0.0, 0.0, 1.0], [1.0; 3]).matrix();
+        let scale = transform([0.0; 3], [0.0, 0.0, 0.0, 1.0], [2.0; 3]).matrix();
+        assert_close(&mul(&IDENTITY, &translate), &translate);
+        assert_close(&mul(&translate, &IDENTITY), &translate);
+        // Scales first, then translates
+        assert_close(&mul(&translate, &scale), &transform([1.0, 2.0, 3.0], [0.0, 0.0, 0.0, 1.0], [2.0; 3]).matrix());
+        // Scales the translation too
+        assert_close(&mul(&scale, &translate), &transform([2.0, 4.0, 6.0], [0.0, 0.0, 0.0, 1.0], [2.0; 3]).matrix());
+    }
+
+    #[test]
+    fn flatten_columns() {
+        let m = [[0.0, 1.0, 2.0, 3.0], [4.0, 5.0, 6.0, 7.0], [8.0, 9.0, 10.0, 11.0], [12.0, 13.0, 14.0, 15.0]];
+        assert_eq!(flatten(m), std::array::from_fn(|i| i as f32));
+    }
+
+    #[test]
+    fn nodes_outside_the_default_scene() {
+        let translate = |x| transform([x, 0.0, 0.0], [0.0, 0.0, 0.0, 1.0], [1.0; 3]);
+        let scene = GltfScene {
+            meshes: Vec::new(),
+            materials: Vec::new(),
+            textures: Vec::new(),
+            samplers: Vec::new(),
+            nodes: vec![
+                node(None, vec![1], translate(1.0)),
+                node(Some(0), Vec::new(), translate(2.0)),
+                node(None, vec![3], translate(10.0)),
+                node(Some(2), Vec::new(), translate(20.0)),
+            ],
+            roots: vec![0],
+            skins: Vec::new(),
+            animations: Vec::new(),
+        };
+        let world = scene.world_matrices();
+        assert_eq!(world.iter().map(|m| m[12]).collect::<Vec<f32>>(), [1.0, 3.0, 10.0, 30.0]);
+    }
+
+    #[test]
+    fn sampler_defaults() {
+        let gltf = gltf::Gltf::from_slice(br#"{
+            "asset": { "version": "2.0" },
+            "samplers": [
+                {},
+                { "magFilter": 9728, "minFilter": 9984, "wrapS": 33071, "wrapT": 33648 },
+                { "minFilter": 9729 }
+            ]
+        }"#).unwrap();
+        let samplers = gltf.document.samplers().map(|s| sampler_settings(&s)).collect::<Vec<SamplerSettings>>();
+        assert_eq!(samplers[0], SamplerSettings::default());
+        assert_eq!(samplers[1], SamplerSettings {
+            wrap: [WrapMode::ClampToEdge, WrapMode::MirroredRepeat, WrapMode::Repeat],
+            min_filter: Filter::Nearest,
+            mag_filter: Filter::Nearest,
+            mipmap_filter: Some(Filter::Nearest),
+            ..Default::default()
+        });
+        assert_eq!(samplers[2].min_filter, Filter::Linear);
+        assert_eq!(samplers[2].mipmap_filter, None);
+        assert_eq!(samplers[2].wrap, [WrapMode::Repeat; 3]);
+    }
+}
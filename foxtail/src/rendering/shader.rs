@@ -1,81 +1,132 @@
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use glow::*;
 
-fn format_shader_errors(src: &str, log: &str) -> String {
-    let src_split = src.lines().collect::<Vec<&str>>();
-    let mut formatted_errors = String::new();
-    for line in log.lines() {
-        let line_split = line.split(":").collect::<Vec<&str>>();
-        let line_number = line_split.get(0).map(|s| {
-            let mut s = s.trim().to_string();
-            s = s.replace("0(", "");
-            s.pop();
-            s.parse::<usize>().map(|i| i.wrapping_sub(1)).unwrap_or(0)
-        }).unwrap_or(0);
-        let err_code = line_split.get(1).map(|s| {
-            let mut s = s.trim().to_string();
-            s = s.replace("error ", "");
-            s
-        }).unwrap_or(String::from(""));
-        let err_line = line_split.get(2).map(|s| s.trim()).unwrap_or("");
-
-        let mut var_name: Option<String> = None;
-        if &err_code == "C1503" && err_line.contains("undefined variable") {
-            let mut var = err_line.replace("undefined variable", "").trim().to_string();
-            var = var[1..].to_string();
-            var.pop();
-            var_name = Some(var);
-        }
-
-        formatted_errors.push_str("\x1b[1;31m");
-        formatted_errors.push_str(line);
-        formatted_errors.push_str("\x1b[0m");
-        formatted_errors.push('\n');
-        let min = line_number.wrapping_sub(1).min(line_number);
-        let max = line_number.wrapping_add(1).max(line_number);
-        let line_num_str = line_number.wrapping_add(1).to_string();
-        for i in min..=max {
-            formatted_errors.push_str("\x1b[1;36m");
-            if i == line_number {
-                formatted_errors.push_str(&format!(" {} | ", line_num_str));
-            } else {
-                for _ in 0..(line_num_str.len()+2) { formatted_errors.push(' '); }
-                formatted_errors.push_str("| ");
-            }
-            formatted_errors.push_str("\x1b[22;0m");
-            let mut code_line = src_split.get(i).map(|s| *s).unwrap_or("CODE NOT FOUND").to_string();
-            if let Some(var_name) = var_name.as_ref() {
-                code_line = code_line.replace(var_name, &format!("\x1b[1;33m{}\x1b[22;0m", var_name));
-            }
-            formatted_errors.push_str(&code_line);
-            if i == line_number { formatted_errors.push_str("\x1b[1;31m <- Error occurs here\x1b[22;0m"); }
-            formatted_errors.push('\n');
-        }
-        formatted_errors.push('\n');
-    }
-    formatted_errors
-}
+use super::binding::BindingStack;
+use super::barrier::{BindingPoint, HazardTracker, MemoryBarrier, Resource};
+use super::gl_ext::GlExt;
+use super::reflection::ShaderReflection;
+use super::buffer::FixedSizeBuffer;
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::atomic_counter::AtomicCounter;
+use super::texture::ImageTexture;
+use super::program_cache::ProgramCache;
+use super::spirv::{compile_spirv_stage, supports_spirv, SpirvModule};
+use super::diagnostics::{format_diagnostics, parse_shader_log, Severity, ShaderDiagnostic};
+
+#[cfg(feature = "validation")]
+pub use super::validation::{validate, validate_program, validate_program_files, validate_program_files_strict, validate_program_strict, validate_strict, SKIPPED_STAGE};
 
 unsafe fn compile_stage(gl: &Context, name: &str, stage: u32, src: &str) -> NativeShader {
     let shader = gl.create_shader(stage).expect("Failed to create shader!");
     gl.shader_source(shader, src);
     gl.compile_shader(shader);
+    let log = gl.get_shader_info_log(shader);
     if !gl.get_shader_compile_status(shader) {
-        let log = gl.get_shader_info_log(shader);
         error!("Shader compile error: {}", log);
-        let formatted_errors = format_shader_errors(src, &log);
+        let formatted_errors = format_diagnostics(src, &parse_shader_log(&log));
         panic!("Failed to compile shader (`{}`)! Errors:\n{}", name, formatted_errors);
     }
+    let warnings = parse_shader_log(&log).into_iter().filter(|d| d.severity != Severity::Info).collect::<Vec<ShaderDiagnostic>>();
+    if !warnings.is_empty() {
+        warn!("Shader (`{}`) compiled with warnings:\n{}", name, format_diagnostics(src, &warnings));
+    }
     shader
 }
 
+/// Storage blocks, atomic counters and images a shader bound through its `UniformInterface`
+#[derive(Default)]
+struct InterfaceBindings(Mutex<HashMap<BindingPoint, Resource>>);
+
+impl InterfaceBindings {
+    fn insert(&self, point: BindingPoint, res: Resource) {
+        self.0.lock().unwrap().insert(point, res);
+    }
+
+    /// Unbinds whatever is still bound where the shader left it, for when the shader is dropped
+    fn release(&self, gl: &Context, ext: &GlExt, tracker: &HazardTracker) {
+        for (point, res) in self.0.lock().unwrap().drain() {
+            let (binding, index) = match point {
+                BindingPoint::Storage(index) => (SHADER_STORAGE_BUFFER_BINDING, index),
+                BindingPoint::AtomicCounter(index) => (ATOMIC_COUNTER_BUFFER_BINDING, index),
+                BindingPoint::Image(index) => (IMAGE_BINDING_NAME, index),
+                BindingPoint::Texture(_) => continue,
+            };
+            if unsafe { gl.get_parameter_indexed_i32(binding, index) } as u32 != res.name() {
+                continue;
+            }
+            unsafe {
+                match point {
+                    BindingPoint::Storage(index) => gl.bind_buffer_base(SHADER_STORAGE_BUFFER, index, None),
+                    BindingPoint::AtomicCounter(index) => gl.bind_buffer_base(ATOMIC_COUNTER_BUFFER, index, None),
+                    BindingPoint::Image(index) => ext.unbind_image_texture(index),
+                    BindingPoint::Texture(_) => {},
+                }
+            }
+            tracker.unbind_write(point);
+            tracker.unbind_read(point, None);
+        }
+    }
+}
+
+/// Storage blocks, atomic counters and images bound through it stay bound after the closure returns,
+/// so a following dispatch or draw sees them. They are unbound when something else is bound in
+/// their place, or when the shader is dropped.
 pub struct UniformInterface<'u> {
     bound_shader: &'u NativeProgram,
+    reflection: &'u ShaderReflection,
     gl: Arc<Context>,
+    bound: &'u InterfaceBindings,
 }
 
 impl<'u> UniformInterface<'u> {
+    fn new(program: &'u NativeProgram, reflection: &'u ShaderReflection, gl: &Arc<Context>, bound: &'u InterfaceBindings) -> Self {
+        Self {
+            bound_shader: program,
+            reflection,
+            gl: gl.clone(),
+            bound,
+        }
+    }
+
+    /// Binds `buffer` to the binding of the shader storage block called `name`
+    pub fn bind_storage<T>(&self, name: &str, buffer: &FixedSizeBuffer<T>) {
+        let block = match self.reflection.storage_blocks.get(name) {
+            Some(block) => block,
+            None => {
+                debug!("Shader has no active storage block `{}`!", name);
+                return;
+            }
+        };
+        if cfg!(debug_assertions) && buffer.size() < block.data_size {
+            warn!("Buffer bound to storage block `{}` is {} bytes, but the block needs at least {} bytes!", name, buffer.size(), block.data_size);
+        }
+        buffer.bind_base(block.binding);
+        self.bound.insert(BindingPoint::Storage(block.binding), Resource::buffer(unsafe { buffer.buf() }));
+    }
+
+    /// Binds `counter` to the buffer binding of the atomic counter called `name`
+    pub fn bind_atomic_counter(&self, name: &str, counter: &AtomicCounter) {
+        match self.reflection.atomic_counters.get(name) {
+            Some(binding) => {
+                counter.bind_base(*binding);
+                self.bound.insert(BindingPoint::AtomicCounter(*binding), Resource::buffer(counter.buf()));
+            },
+            None => debug!("Shader has no active atomic counter `{}`!", name),
+        }
+    }
+
+    /// Binds `texture` as an image to the unit of the image uniform called `name`
+    pub fn bind_image<T: ImageTexture>(&self, name: &str, texture: &T, write: bool, read: bool) {
+        match self.reflection.images.get(name) {
+            Some(unit) => {
+                texture.bind_img(*unit, write, read);
+                self.bound.insert(BindingPoint::Image(*unit), Resource::texture(unsafe { texture.tex() }));
+            },
+            None => debug!("Shader has no active image `{}`!", name),
+        }
+    }
+
     pub fn set_f32(&self, name: &str, val: f32) {
         let loc = unsafe { self.gl.get_uniform_location(*self.bound_shader, name) };
         unsafe { self.gl.uniform_1_f32(loc.as_ref(), val); }
@@ -152,14 +203,183 @@ impl<'u> UniformInterface<'u> {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    TessControl,
+    TessEvaluation,
+    Geometry,
+    Fragment,
+    Compute,
+}
+
+impl ShaderStage {
+    pub(crate) fn to_gl(self) -> u32 {
+        match self {
+            Self::Vertex => VERTEX_SHADER,
+            Self::TessControl => TESS_CONTROL_SHADER,
+            Self::TessEvaluation => TESS_EVALUATION_SHADER,
+            Self::Geometry => GEOMETRY_SHADER,
+            Self::Fragment => FRAGMENT_SHADER,
+            Self::Compute => COMPUTE_SHADER,
+        }
+    }
+}
+
+/// Compiles and links a program from `(stage, source, name)` triples.
+/// Goes through the program binary cache first, if there is one.
+unsafe fn link_program(gl: &Context, cache: Option<&ProgramCache>, stages: &[(ShaderStage, &str, &str)]) -> NativeProgram {
+    let key = cache.map(|cache| cache.key(stages));
+    if let (Some(cache), Some(key)) = (cache, key) {
+        if let Some(program) = cache.load(gl, key) {
+            return program;
+        }
+    }
+
+    let program = gl.create_program().expect("Failed to create shader program!");
+    if let Some(cache) = cache {
+        cache.prepare(program);
+    }
+
+    let shaders = stages.iter().map(|(stage, src, name)| compile_stage(gl, name, stage.to_gl(), src)).collect::<Vec<NativeShader>>();
+    link_shaders(gl, program, shaders);
+
+    if let (Some(cache), Some(key)) = (cache, key) {
+        cache.store(key, program);
+    }
+
+    program
+}
+
+/// Links compiled stages into `program` and deletes them
+unsafe fn link_shaders(gl: &Context, program: NativeProgram, shaders: Vec<NativeShader>) {
+    for shader in &shaders {
+        gl.attach_shader(program, *shader);
+    }
+    gl.link_program(program);
+    if !gl.get_program_link_status(program) {
+        error!("Program link error: {}", gl.get_program_info_log(program));
+        panic!("Failed to link program!");
+    }
+    for shader in shaders {
+        gl.detach_shader(program, shader);
+        gl.delete_shader(shader);
+    }
+}
+
+/// Links a program from SPIR-V modules. These don't go through the program cache.
+/// Panics if the driver doesn't support SPIR-V!
+unsafe fn link_spirv_program(gl: &Context, ext: &GlExt, stages: &[(ShaderStage, &SpirvModule)]) -> NativeProgram {
+    if !supports_spirv(gl, ext) {
+        panic!("SPIR-V shaders are not supported by this driver!");
+    }
+    let program = gl.create_program().expect("Failed to create shader program!");
+    let shaders = stages.iter().map(|(stage, module)| compile_spirv_stage(gl, ext, stage.to_gl(), module)).collect::<Vec<NativeShader>>();
+    link_shaders(gl, program, shaders);
+    program
+}
+
+/// Builds a `Shader` with optional geometry and tessellation stages
+pub struct ShaderBuilder<'s> {
+    vs: (&'s str, &'s str),
+    fs: (&'s str, &'s str),
+    gs: Option<(&'s str, &'s str)>,
+    tcs: Option<(&'s str, &'s str)>,
+    tes: Option<(&'s str, &'s str)>,
+    patch_vertices: Option<u32>,
+}
+
+impl<'s> ShaderBuilder<'s> {
+    pub fn new(vs: (&'s str, &'s str), fs: (&'s str, &'s str)) -> Self {
+        Self {
+            vs,
+            fs,
+            gs: None,
+            tcs: None,
+            tes: None,
+            patch_vertices: None,
+        }
+    }
+
+    pub fn geometry(mut self, gs: (&'s str, &'s str)) -> Self {
+        self.gs = Some(gs);
+        self
+    }
+
+    pub fn tess_control(mut self, tcs: (&'s str, &'s str)) -> Self {
+        self.tcs = Some(tcs);
+        self
+    }
+
+    pub fn tess_evaluation(mut self, tes: (&'s str, &'s str)) -> Self {
+        self.tes = Some(tes);
+        self
+    }
+
+    /// Number of vertices per patch, set whenever the shader gets bound.
+    /// Defaults to 3 if the shader has tessellation stages.
+    pub fn patch_vertices(mut self, count: u32) -> Self {
+        self.patch_vertices = Some(count);
+        self
+    }
+
+    pub fn build(self, renderer: &super::Renderer) -> Shader {
+        let gl = renderer.gl.clone();
+        let bindings = renderer.bindings.clone();
+        let cache = renderer.program_cache.lock().unwrap();
+        self.build_from_gl(gl, &renderer.ext, cache.as_ref(), renderer.tracker.clone(), bindings, renderer.resources.clone())
+    }
+
+    pub(crate) fn build_from_gl(self, gl: Arc<Context>, ext: &Arc<GlExt>, cache: Option<&ProgramCache>, tracker: Arc<HazardTracker>, bindings: Arc<BindingStack>, resources: Arc<ResourceRegistry>) -> Shader {
+        if self.tcs.is_some() && self.tes.is_none() {
+            panic!("A tessellation control stage requires a tessellation evaluation stage!");
+        }
+        let tessellated = self.tes.is_some();
+        if self.patch_vertices.is_some() && !tessellated {
+            warn!("Patch vertices set on a shader without tessellation stages!");
+        }
+
+        let mut stages = vec![(ShaderStage::Vertex, self.vs.0, self.vs.1)];
+        if let Some((src, name)) = self.tcs { stages.push((ShaderStage::TessControl, src, name)); }
+        if let Some((src, name)) = self.tes { stages.push((ShaderStage::TessEvaluation, src, name)); }
+        if let Some((src, name)) = self.gs { stages.push((ShaderStage::Geometry, src, name)); }
+        stages.push((ShaderStage::Fragment, self.fs.0, self.fs.1));
+
+        let program = unsafe { link_program(&gl, cache, &stages) };
+        let reflection = ShaderReflection::reflect(&gl, ext, program);
+        let label = stages.iter().map(|(_, _, name)| *name).collect::<Vec<&str>>().join(" + ");
+        resources.register(ResourceKind::Program, program.0.get(), 0, &label);
+
+        Shader {
+            program: program,
+            reflection,
+            patch_vertices: if tessellated { Some(self.patch_vertices.unwrap_or(3) as i32) } else { None },
+            gl: gl,
+            ext: ext.clone(),
+            tracker,
+            bindings,
+            interface_bindings: InterfaceBindings::default(),
+            resources,
+        }
+    }
+}
+
 pub struct Shader {
     program: NativeProgram,
+    reflection: ShaderReflection,
+    patch_vertices: Option<i32>,
     gl: Arc<Context>,
-    shader_bound: Arc<AtomicBool>,
+    ext: Arc<GlExt>,
+    tracker: Arc<HazardTracker>,
+    bindings: Arc<BindingStack>,
+    interface_bindings: InterfaceBindings,
+    resources: Arc<ResourceRegistry>,
 }
 
 impl Drop for Shader {
     fn drop(&mut self) {
+        self.interface_bindings.release(&self.gl, &self.ext, &self.tracker);
+        self.resources.unregister(ResourceKind::Program, self.program.0.get());
         unsafe {
             self.gl.delete_program(self.program);
         }
@@ -167,74 +387,82 @@ impl Drop for Shader {
 }
 
 impl Shader {
-    pub fn new(renderer: &super::Renderer, (vs, vs_name): (&str, &str), (fs, fs_name): (&str, &str)) -> Self {
-        let gl = renderer.gl.clone();
-        let shader_bound = renderer.shader_bound.clone();
-        Self::new_from_gl(gl, shader_bound, vs, vs_name, fs, fs_name)
+    pub fn new(renderer: &super::Renderer, vs: (&str, &str), fs: (&str, &str)) -> Self {
+        ShaderBuilder::new(vs, fs).build(renderer)
     }
 
-    pub(crate) fn new_from_gl(gl: Arc<Context>, shader_bound: Arc<AtomicBool>, vs: &str, vs_name: &str, fs: &str, fs_name: &str) -> Self {
-        unsafe {
-            let program = gl.create_program().expect("Failed to create shader program!");
+    /// Like `new`, but from SPIR-V modules compiled offline
+    pub fn from_spirv(renderer: &super::Renderer, vs: SpirvModule, fs: SpirvModule) -> Self {
+        let gl = renderer.gl.clone();
+        let program = unsafe { link_spirv_program(&gl, &renderer.ext, &[(ShaderStage::Vertex, &vs), (ShaderStage::Fragment, &fs)]) };
+        let reflection = ShaderReflection::reflect(&gl, &renderer.ext, program);
+        renderer.resources.register(ResourceKind::Program, program.0.get(), 0, &format!("{} + {}", vs.name(), fs.name()));
+
+        Self {
+            program,
+            reflection,
+            patch_vertices: None,
+            gl,
+            ext: renderer.ext.clone(),
+            tracker: renderer.tracker.clone(),
+            bindings: renderer.bindings.clone(),
+            interface_bindings: InterfaceBindings::default(),
+            resources: renderer.resources.clone(),
+        }
+    }
 
-            let vs_shader = compile_stage(&gl, &vs_name, VERTEX_SHADER, vs);
-            let fs_shader = compile_stage(&gl, &fs_name, FRAGMENT_SHADER, fs);
+    pub fn builder<'s>(vs: (&'s str, &'s str), fs: (&'s str, &'s str)) -> ShaderBuilder<'s> {
+        ShaderBuilder::new(vs, fs)
+    }
 
-            gl.attach_shader(program, vs_shader);
-            gl.attach_shader(program, fs_shader);
-            gl.link_program(program);
-            if !gl.get_program_link_status(program) {
-                error!("Program link error: {}", gl.get_program_info_log(program));
-                panic!("Failed to link program!");
-            }
-            gl.detach_shader(program, vs_shader);
-            gl.detach_shader(program, fs_shader);
-            gl.delete_shader(vs_shader);
-            gl.delete_shader(fs_shader);
-
-            Self {
-                program: program,
-                gl: gl,
-                shader_bound: shader_bound,
-            }
-        }
+    pub(crate) fn new_from_gl(gl: Arc<Context>, ext: &Arc<GlExt>, tracker: Arc<HazardTracker>, bindings: Arc<BindingStack>, resources: Arc<ResourceRegistry>, vs: (&str, &str), fs: (&str, &str)) -> Self {
+        ShaderBuilder::new(vs, fs).build_from_gl(gl, ext, None, tracker, bindings, resources)
     }
 
-    fn bind(&self) {
-        unsafe {
-            self.gl.use_program(Some(self.program));
-            self.shader_bound.store(true, Ordering::Release);
-        }
+    /// Drops the program from the registry early, for objects the renderer owns itself
+    pub(crate) fn forget_resource(&self) {
+        self.resources.unregister(ResourceKind::Program, self.program.0.get());
     }
 
-    fn unbind(&self) {
-        unsafe {
-            self.gl.use_program(None);
-            self.shader_bound.store(false, Ordering::Release);
-        }
+    /// Storage blocks, atomic counters and images of the shader, by name
+    pub fn reflection(&self) -> &ShaderReflection {
+        &self.reflection
     }
 
-    /// Runs a closure while the shader is bound
+    /// Runs a closure while the shader is bound, the previously bound shader is bound again afterwards
     pub fn while_bound<F: FnOnce(UniformInterface) -> Result<(), super::RenderError>>(&self, f: F) -> Result<(), super::RenderError> {
-        self.bind();
-        let uni = UniformInterface {
-            bound_shader: &self.program,
-            gl: self.gl.clone(),
-        };
-        f(uni)?;
-        self.unbind();
-        Ok(())
+        let _guard = self.bindings.push_program(self.program, self.patch_vertices);
+        let uni = UniformInterface::new(&self.program, &self.reflection, &self.gl, &self.interface_bindings);
+        f(uni)
     }
 }
 
+/// Arguments for `ComputeShader::dispatch_indirect`, laid out like GL expects them
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DispatchIndirectCommand {
+    pub num_groups_x: u32,
+    pub num_groups_y: u32,
+    pub num_groups_z: u32,
+}
+
 pub struct ComputeShader {
     program: NativeProgram,
+    reflection: ShaderReflection,
+    local_size: [u32; 3],
+    max_groups: [u32; 3],
     gl: Arc<Context>,
-    shader_bound: Arc<AtomicBool>,
+    ext: Arc<GlExt>,
+    tracker: Arc<HazardTracker>,
+    bindings: Arc<BindingStack>,
+    interface_bindings: InterfaceBindings,
+    resources: Arc<ResourceRegistry>,
 }
 
 impl Drop for ComputeShader {
     fn drop(&mut self) {
+        self.interface_bindings.release(&self.gl, &self.ext, &self.tracker);
+        self.resources.unregister(ResourceKind::Program, self.program.0.get());
         unsafe {
             self.gl.delete_program(self.program);
         }
@@ -244,73 +472,104 @@ impl Drop for ComputeShader {
 impl ComputeShader {
     pub fn new(renderer: &super::Renderer, (cs, cs_name): (&str, &str)) -> Self {
         let gl = renderer.gl.clone();
-        let shader_bound = renderer.shader_bound.clone();
-        Self::new_from_gl(gl, shader_bound, cs, cs_name)
+        let bindings = renderer.bindings.clone();
+        let cache = renderer.program_cache.lock().unwrap();
+        Self::new_from_gl(gl, &renderer.ext, cache.as_ref(), renderer.tracker.clone(), bindings, renderer.resources.clone(), (cs, cs_name))
     }
 
-    pub(crate) fn new_from_gl(gl: Arc<Context>, shader_bound: Arc<AtomicBool>, cs: &str, cs_name: &str) -> Self {
-        unsafe {
-            let program = gl.create_program().expect("Failed to create shader program!");
-
-            let cs_shader = compile_stage(&gl, cs_name, COMPUTE_SHADER, cs);
+    /// Like `new`, but from a SPIR-V module compiled offline.
+    /// The local size can come from specialization constants (`local_size_x_id`).
+    pub fn from_spirv(renderer: &super::Renderer, cs: SpirvModule) -> Self {
+        let gl = renderer.gl.clone();
+        let program = unsafe { link_spirv_program(&gl, &renderer.ext, &[(ShaderStage::Compute, &cs)]) };
+        Self::from_program(gl, &renderer.ext, program, renderer.tracker.clone(), renderer.bindings.clone(), renderer.resources.clone(), cs.name())
+    }
 
-            gl.attach_shader(program, cs_shader);
-            gl.link_program(program);
-            if !gl.get_program_link_status(program) {
-                error!("Program link error: {}", gl.get_program_info_log(program));
-                panic!("Failed to link program!");
-            }
-            gl.detach_shader(program, cs_shader);
-            gl.delete_shader(cs_shader);
+    pub(crate) fn new_from_gl(gl: Arc<Context>, ext: &Arc<GlExt>, cache: Option<&ProgramCache>, tracker: Arc<HazardTracker>, bindings: Arc<BindingStack>, resources: Arc<ResourceRegistry>, (cs, cs_name): (&str, &str)) -> Self {
+        let program = unsafe { link_program(&gl, cache, &[(ShaderStage::Compute, cs, cs_name)]) };
+        Self::from_program(gl, ext, program, tracker, bindings, resources, cs_name)
+    }
 
-            Self {
-                program: program,
-                gl: gl,
-                shader_bound: shader_bound,
-            }
+    fn from_program(gl: Arc<Context>, ext: &Arc<GlExt>, program: NativeProgram, tracker: Arc<HazardTracker>, bindings: Arc<BindingStack>, resources: Arc<ResourceRegistry>, cs_name: &str) -> Self {
+        let mut local_size = [0i32; 3];
+        let max_groups = unsafe {
+            ext.get_program_parameter_i32_slice(program, COMPUTE_WORK_GROUP_SIZE, &mut local_size);
+            [0, 1, 2].map(|i| gl.get_parameter_indexed_i32(MAX_COMPUTE_WORK_GROUP_COUNT, i) as u32)
+        };
+        trace!("Compute shader `{}` has local size {:?}", cs_name, local_size);
+        let reflection = ShaderReflection::reflect(&gl, ext, program);
+        resources.register(ResourceKind::Program, program.0.get(), 0, cs_name);
+
+        Self {
+            program: program,
+            reflection,
+            local_size: [local_size[0] as u32, local_size[1] as u32, local_size[2] as u32],
+            max_groups,
+            gl: gl,
+            ext: ext.clone(),
+            tracker,
+            bindings,
+            interface_bindings: InterfaceBindings::default(),
+            resources,
         }
     }
 
+    /// Sets uniforms and binds resources for the following `dispatch` calls
     pub fn set_uniforms<F: FnOnce(UniformInterface)>(&self, f: F) {
-        self.bind();
-        let uni = UniformInterface {
-            bound_shader: &self.program,
-            gl: self.gl.clone(),
-        };
+        let _guard = self.bindings.push_program(self.program, None);
+        let uni = UniformInterface::new(&self.program, &self.reflection, &self.gl, &self.interface_bindings);
         f(uni);
-        self.unbind();
     }
 
-    fn bind(&self) {
-        unsafe {
-            self.gl.use_program(Some(self.program));
-            self.shader_bound.store(true, Ordering::Release);
-        }
+    /// Runs a closure while the shader is bound, the previously bound shader is bound again afterwards
+    pub fn while_bound<F: FnOnce(UniformInterface) -> Result<(), super::RenderError>>(&self, f: F) -> Result<(), super::RenderError> {
+        let _guard = self.bindings.push_program(self.program, None);
+        let uni = UniformInterface::new(&self.program, &self.reflection, &self.gl, &self.interface_bindings);
+        f(uni)
     }
 
-    fn unbind(&self) {
-        unsafe {
-            self.gl.use_program(None);
-            self.shader_bound.store(false, Ordering::Release);
-        }
+    /// Storage blocks, atomic counters and images of the shader, by name
+    pub fn reflection(&self) -> &ShaderReflection {
+        &self.reflection
     }
 
-    /// Runs a closure while the shader is bound
-    pub fn while_bound<F: FnOnce(UniformInterface) -> Result<(), super::RenderError>>(&self, f: F) -> Result<(), super::RenderError> {
-        self.bind();
-        let uni = UniformInterface {
-            bound_shader: &self.program,
-            gl: self.gl.clone(),
-        };
-        f(uni)?;
-        self.unbind();
-        Ok(())
+    /// The `local_size_x/y/z` declared in the shader
+    pub fn local_size(&self) -> [u32; 3] {
+        self.local_size
     }
 
-    /// Dispatches the compute shader
+    /// Dispatches the compute shader.
+    /// Panics if a group count is larger than `MAX_COMPUTE_WORK_GROUP_COUNT`!
     pub fn dispatch(&self, num_groups: [u32; 3]) {
+        if num_groups.iter().zip(&self.max_groups).any(|(groups, max)| groups > max) {
+            panic!("Cannot dispatch {:?} groups, the maximum is {:?}!", num_groups, self.max_groups);
+        }
+        self.tracker.issuing_commands();
         unsafe {
             self.gl.dispatch_compute(num_groups[0], num_groups[1], num_groups[2]);
         }
+        self.tracker.commands_issued();
+    }
+
+    /// Dispatches enough groups to cover `size` invocations, rounding up to the local size
+    pub fn dispatch_for_size(&self, size: [u32; 3]) {
+        let num_groups = [0, 1, 2].map(|i| size[i].div_ceil(self.local_size[i].max(1)));
+        self.dispatch(num_groups);
+    }
+
+    /// Dispatches with the group counts stored at `index` in `args`, so they can be written on the GPU
+    pub fn dispatch_indirect(&self, args: &super::buffer::FixedSizeBuffer<DispatchIndirectCommand>, index: usize) {
+        let t_size = std::mem::size_of::<DispatchIndirectCommand>();
+        if (index + 1) * t_size > args.size() {
+            panic!("Cannot dispatch past buffer bounds!");
+        }
+        self.tracker.read(Resource::buffer(unsafe { args.buf() }), MemoryBarrier::COMMAND);
+        self.tracker.issuing_commands();
+        unsafe {
+            self.gl.bind_buffer(DISPATCH_INDIRECT_BUFFER, Some(args.buf()));
+            self.gl.dispatch_compute_indirect((index * t_size) as i32);
+            self.gl.bind_buffer(DISPATCH_INDIRECT_BUFFER, None);
+        }
+        self.tracker.commands_issued();
     }
 }
@@ -0,0 +1,33 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(binding = 0) uniform sampler2D equirect;
+// FORMAT is replaced with the image format of the cubemap
+layout(binding = 0, FORMAT) uniform writeonly imageCube cubemap;
+
+const float PI = 3.14159265359;
+
+// Direction through texel `uv` (-1..1) of `face`, following the cubemap face layout of the GL spec
+vec3 face_direction(int face, vec2 uv) {
+    switch (face) {
+        case 0: return vec3(1.0, -uv.y, -uv.x);
+        case 1: return vec3(-1.0, -uv.y, uv.x);
+        case 2: return vec3(uv.x, 1.0, uv.y);
+        case 3: return vec3(uv.x, -1.0, -uv.y);
+        case 4: return vec3(uv.x, -uv.y, 1.0);
+        default: return vec3(-uv.x, -uv.y, -1.0);
+    }
+}
+
+void main() {
+    ivec3 id = ivec3(gl_GlobalInvocationID);
+    ivec2 size = imageSize(cubemap);
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+    vec2 uv = (vec2(id.xy) + 0.5) / vec2(size) * 2.0 - 1.0;
+    vec3 dir = normalize(face_direction(id.z, uv));
+    vec2 equirect_uv = vec2(atan(dir.z, dir.x) / (2.0 * PI) + 0.5, asin(clamp(dir.y, -1.0, 1.0)) / PI + 0.5);
+    imageStore(cubemap, id, textureLod(equirect, equirect_uv, 0.0));
+}
@@ -0,0 +1,99 @@
+//! SPIR-V shader modules (`ARB_gl_spirv`, core in GL 4.6), for shaders compiled offline with glslang or naga.
+//! Names of storage blocks and uniforms only survive if the module was compiled with debug info,
+//! without them `UniformInterface::bind_storage` and friends can't find anything.
+use glow::*;
+
+use super::gl_ext::GlExt;
+use super::diagnostics::{parse_shader_log, Severity};
+
+const SPIRV_MAGIC: u32 = 0x07230203;
+
+/// A SPIR-V binary together with the entry point and specialization constants to use
+#[derive(Debug, Clone)]
+pub struct SpirvModule<'s> {
+    binary: &'s [u8],
+    name: &'s str,
+    entry_point: &'s str,
+    constants: Vec<(u32, u32)>,
+}
+
+impl<'s> SpirvModule<'s> {
+    /// `name` is only used in error messages, like the names GLSL sources are passed with.
+    /// Panics if `binary` doesn't look like SPIR-V!
+    pub fn new(binary: &'s [u8], name: &'s str) -> Self {
+        if binary.len() < 4 || !binary.len().is_multiple_of(4) {
+            panic!("SPIR-V module `{}` has an invalid size of {} bytes!", name, binary.len());
+        }
+        let magic = u32::from_ne_bytes([binary[0], binary[1], binary[2], binary[3]]);
+        if magic != SPIRV_MAGIC {
+            panic!("SPIR-V module `{}` has an invalid magic number {:#010x}!", name, magic);
+        }
+        Self {
+            binary,
+            name,
+            entry_point: "main",
+            constants: Vec::new(),
+        }
+    }
+
+    /// Defaults to `main`
+    pub fn entry_point(mut self, entry_point: &'s str) -> Self {
+        self.entry_point = entry_point;
+        self
+    }
+
+    /// Sets the specialization constant with `constant_id = id`
+    pub fn constant_u32(mut self, id: u32, value: u32) -> Self {
+        self.constants.retain(|(i, _)| *i != id);
+        self.constants.push((id, value));
+        self
+    }
+
+    pub fn constant_i32(self, id: u32, value: i32) -> Self {
+        self.constant_u32(id, value as u32)
+    }
+
+    pub fn constant_f32(self, id: u32, value: f32) -> Self {
+        self.constant_u32(id, value.to_bits())
+    }
+
+    pub fn constant_bool(self, id: u32, value: bool) -> Self {
+        self.constant_u32(id, value as u32)
+    }
+
+    pub fn name(&self) -> &str {
+        self.name
+    }
+}
+
+pub(crate) fn supports_spirv(gl: &Context, ext: &GlExt) -> bool {
+    if !ext.supports_spirv() {
+        return false;
+    }
+    let count = unsafe { gl.get_parameter_i32(NUM_SHADER_BINARY_FORMATS) };
+    if count <= 0 {
+        return false;
+    }
+    let mut formats = vec![0i32; count as usize];
+    unsafe { gl.get_parameter_i32_slice(SHADER_BINARY_FORMATS, &mut formats); }
+    formats.contains(&(SHADER_BINARY_FORMAT_SPIR_V as i32))
+}
+
+/// Loads and specializes a SPIR-V module as a single stage.
+/// Panics with the driver's log if specialization fails!
+pub(crate) unsafe fn compile_spirv_stage(gl: &Context, ext: &GlExt, stage: u32, module: &SpirvModule) -> NativeShader {
+    let shader = gl.create_shader(stage).expect("Failed to create shader!");
+    ext.shader_binary(shader, SHADER_BINARY_FORMAT_SPIR_V, module.binary);
+    let (ids, values): (Vec<u32>, Vec<u32>) = module.constants.iter().copied().unzip();
+    ext.specialize_shader(shader, module.entry_point, &ids, &values);
+    let log = gl.get_shader_info_log(shader);
+    if !gl.get_shader_compile_status(shader) {
+        error!("Shader specialization error: {}", log);
+        let errors = parse_shader_log(&log).iter().map(|d| d.to_string()).collect::<Vec<String>>().join("\n");
+        panic!("Failed to specialize SPIR-V module (`{}`, entry point `{}`)! Errors:\n{}", module.name, module.entry_point, errors);
+    }
+    for warning in parse_shader_log(&log).into_iter().filter(|d| d.severity != Severity::Info) {
+        warn!("SPIR-V module (`{}`): {}", module.name, warning);
+    }
+    shader
+}
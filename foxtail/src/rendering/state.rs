@@ -0,0 +1,390 @@
+//! Fixed function pipeline state. Instead of toggling global flags, a `RenderState` describes all of it
+//! and gets applied for the duration of a closure, see `Renderer::with_state` and `Framebuffer::while_bound_with_state`.
+//! Only the parts that differ from the current state are sent to GL.
+use std::sync::{Arc, Mutex};
+use glow::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareFunc {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl CompareFunc {
+    pub(crate) fn to_gl(self) -> u32 {
+        match self {
+            Self::Never => NEVER,
+            Self::Less => LESS,
+            Self::Equal => EQUAL,
+            Self::LessEqual => LEQUAL,
+            Self::Greater => GREATER,
+            Self::NotEqual => NOTEQUAL,
+            Self::GreaterEqual => GEQUAL,
+            Self::Always => ALWAYS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+    ConstantColor,
+    OneMinusConstantColor,
+    ConstantAlpha,
+    OneMinusConstantAlpha,
+    SrcAlphaSaturate,
+}
+
+impl BlendFactor {
+    fn to_gl(self) -> u32 {
+        match self {
+            Self::Zero => ZERO,
+            Self::One => ONE,
+            Self::SrcColor => SRC_COLOR,
+            Self::OneMinusSrcColor => ONE_MINUS_SRC_COLOR,
+            Self::DstColor => DST_COLOR,
+            Self::OneMinusDstColor => ONE_MINUS_DST_COLOR,
+            Self::SrcAlpha => SRC_ALPHA,
+            Self::OneMinusSrcAlpha => ONE_MINUS_SRC_ALPHA,
+            Self::DstAlpha => DST_ALPHA,
+            Self::OneMinusDstAlpha => ONE_MINUS_DST_ALPHA,
+            Self::ConstantColor => CONSTANT_COLOR,
+            Self::OneMinusConstantColor => ONE_MINUS_CONSTANT_COLOR,
+            Self::ConstantAlpha => CONSTANT_ALPHA,
+            Self::OneMinusConstantAlpha => ONE_MINUS_CONSTANT_ALPHA,
+            Self::SrcAlphaSaturate => SRC_ALPHA_SATURATE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendEquation {
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+impl BlendEquation {
+    fn to_gl(self) -> u32 {
+        match self {
+            Self::Add => FUNC_ADD,
+            Self::Subtract => FUNC_SUBTRACT,
+            Self::ReverseSubtract => FUNC_REVERSE_SUBTRACT,
+            Self::Min => MIN,
+            Self::Max => MAX,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Blend {
+    pub src_color: BlendFactor,
+    pub dst_color: BlendFactor,
+    pub color_equation: BlendEquation,
+    pub src_alpha: BlendFactor,
+    pub dst_alpha: BlendFactor,
+    pub alpha_equation: BlendEquation,
+}
+
+impl Blend {
+    /// Regular alpha blending, what `Context::enable_alpha_blending` used to set
+    pub const ALPHA: Self = Self::separate(BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha, BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha);
+    pub const PREMULTIPLIED_ALPHA: Self = Self::separate(BlendFactor::One, BlendFactor::OneMinusSrcAlpha, BlendFactor::One, BlendFactor::OneMinusSrcAlpha);
+    pub const ADDITIVE: Self = Self::separate(BlendFactor::One, BlendFactor::One, BlendFactor::One, BlendFactor::One);
+
+    pub const fn separate(src_color: BlendFactor, dst_color: BlendFactor, src_alpha: BlendFactor, dst_alpha: BlendFactor) -> Self {
+        Self {
+            src_color,
+            dst_color,
+            color_equation: BlendEquation::Add,
+            src_alpha,
+            dst_alpha,
+            alpha_equation: BlendEquation::Add,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CullSide {
+    Front,
+    Back,
+    FrontAndBack,
+}
+
+impl CullSide {
+    fn to_gl(self) -> u32 {
+        match self {
+            Self::Front => FRONT,
+            Self::Back => BACK,
+            Self::FrontAndBack => FRONT_AND_BACK,
+        }
+    }
+}
+
+/// Winding order of front facing triangles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontFace {
+    Cw,
+    Ccw,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    Replace,
+    Increment,
+    IncrementWrap,
+    Decrement,
+    DecrementWrap,
+    Invert,
+}
+
+impl StencilOp {
+    fn to_gl(self) -> u32 {
+        match self {
+            Self::Keep => KEEP,
+            Self::Zero => ZERO,
+            Self::Replace => REPLACE,
+            Self::Increment => INCR,
+            Self::IncrementWrap => INCR_WRAP,
+            Self::Decrement => DECR,
+            Self::DecrementWrap => DECR_WRAP,
+            Self::Invert => INVERT,
+        }
+    }
+}
+
+/// Applies to both front and back faces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stencil {
+    pub func: CompareFunc,
+    pub reference: i32,
+    pub read_mask: u32,
+    pub write_mask: u32,
+    pub stencil_fail: StencilOp,
+    pub depth_fail: StencilOp,
+    pub pass: StencilOp,
+}
+
+impl Default for Stencil {
+    fn default() -> Self {
+        Self {
+            func: CompareFunc::Always,
+            reference: 0,
+            read_mask: !0,
+            write_mask: !0,
+            stencil_fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolygonMode {
+    Fill,
+    Line,
+    Point,
+}
+
+impl PolygonMode {
+    fn to_gl(self) -> u32 {
+        match self {
+            Self::Fill => FILL,
+            Self::Line => LINE,
+            Self::Point => POINT,
+        }
+    }
+}
+
+/// Everything about how primitives get rasterized and written to the framebuffer.
+/// `None` disables the respective test or stage. The default matches GL's defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderState {
+    pub blend: Option<Blend>,
+    pub depth_test: Option<CompareFunc>,
+    pub depth_write: bool,
+    pub cull: Option<CullSide>,
+    pub front_face: FrontFace,
+    /// Red, green, blue, alpha
+    pub color_mask: [bool; 4],
+    pub stencil: Option<Stencil>,
+    /// `[x, y, width, height]` in pixels
+    pub scissor: Option<[i32; 4]>,
+    pub polygon_mode: PolygonMode,
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self {
+            blend: None,
+            depth_test: None,
+            depth_write: true,
+            cull: None,
+            front_face: FrontFace::Ccw,
+            color_mask: [true; 4],
+            stencil: None,
+            scissor: None,
+            polygon_mode: PolygonMode::Fill,
+        }
+    }
+}
+
+impl RenderState {
+    /// Depth tested opaque geometry with back faces culled
+    pub fn opaque() -> Self {
+        Self {
+            depth_test: Some(CompareFunc::Less),
+            cull: Some(CullSide::Back),
+            ..Default::default()
+        }
+    }
+
+    /// Alpha blended geometry, depth tested without writing depth
+    pub fn transparent() -> Self {
+        Self {
+            blend: Some(Blend::ALPHA),
+            depth_test: Some(CompareFunc::Less),
+            depth_write: false,
+            ..Default::default()
+        }
+    }
+}
+
+fn set_enabled(gl: &Context, cap: u32, enabled: bool) {
+    unsafe {
+        if enabled {
+            gl.enable(cap);
+        } else {
+            gl.disable(cap);
+        }
+    }
+}
+
+/// The state GL is currently in, so applying a `RenderState` only touches what changed
+pub(crate) struct StateCache {
+    current: Mutex<RenderState>,
+    gl: Arc<Context>,
+}
+
+impl StateCache {
+    pub(crate) fn new(gl: Arc<Context>) -> Self {
+        let cache = Self {
+            current: Mutex::new(RenderState::default()),
+            gl,
+        };
+        cache.invalidate();
+        cache
+    }
+
+    /// Applies `state` until the guard is dropped
+    pub(crate) fn push(&self, state: &RenderState) -> StateGuard<'_> {
+        StateGuard {
+            previous: self.apply(state),
+            cache: self,
+        }
+    }
+
+    /// Applies `state` and returns the state that was current before
+    pub(crate) fn apply(&self, state: &RenderState) -> RenderState {
+        let mut current = self.current.lock().unwrap();
+        Self::apply_diff(&self.gl, &current, state, false);
+        std::mem::replace(&mut *current, *state)
+    }
+
+    /// Changes part of the current state
+    pub(crate) fn modify<F: FnOnce(&mut RenderState)>(&self, f: F) {
+        let mut state = *self.current.lock().unwrap();
+        f(&mut state);
+        self.apply(&state);
+    }
+
+    /// Sends the whole current state to GL again, for after something else changed it behind our back
+    pub(crate) fn invalidate(&self) {
+        let current = self.current.lock().unwrap();
+        Self::apply_diff(&self.gl, &current, &current, true);
+    }
+
+    fn apply_diff(gl: &Context, old: &RenderState, new: &RenderState, force: bool) {
+        unsafe {
+            if force || old.blend != new.blend {
+                set_enabled(gl, BLEND, new.blend.is_some());
+                if let Some(blend) = new.blend {
+                    gl.blend_func_separate(blend.src_color.to_gl(), blend.dst_color.to_gl(), blend.src_alpha.to_gl(), blend.dst_alpha.to_gl());
+                    gl.blend_equation_separate(blend.color_equation.to_gl(), blend.alpha_equation.to_gl());
+                }
+            }
+            if force || old.depth_test != new.depth_test {
+                set_enabled(gl, DEPTH_TEST, new.depth_test.is_some());
+                if let Some(func) = new.depth_test {
+                    gl.depth_func(func.to_gl());
+                }
+            }
+            if force || old.depth_write != new.depth_write {
+                gl.depth_mask(new.depth_write);
+            }
+            if force || old.cull != new.cull {
+                set_enabled(gl, CULL_FACE, new.cull.is_some());
+                if let Some(side) = new.cull {
+                    gl.cull_face(side.to_gl());
+                }
+            }
+            if force || old.front_face != new.front_face {
+                gl.front_face(if new.front_face == FrontFace::Cw { CW } else { CCW });
+            }
+            if force || old.color_mask != new.color_mask {
+                let [r, g, b, a] = new.color_mask;
+                gl.color_mask(r, g, b, a);
+            }
+            if force || old.stencil != new.stencil {
+                set_enabled(gl, STENCIL_TEST, new.stencil.is_some());
+                // The write mask also applies to clears, so reset it when the test gets disabled
+                let stencil = new.stencil.unwrap_or_default();
+                gl.stencil_func(stencil.func.to_gl(), stencil.reference, stencil.read_mask);
+                gl.stencil_mask(stencil.write_mask);
+                gl.stencil_op(stencil.stencil_fail.to_gl(), stencil.depth_fail.to_gl(), stencil.pass.to_gl());
+            }
+            if force || old.scissor != new.scissor {
+                set_enabled(gl, SCISSOR_TEST, new.scissor.is_some());
+                if let Some([x, y, width, height]) = new.scissor {
+                    gl.scissor(x, y, width, height);
+                }
+            }
+            if force || old.polygon_mode != new.polygon_mode {
+                gl.polygon_mode(FRONT_AND_BACK, new.polygon_mode.to_gl());
+            }
+        }
+    }
+}
+
+/// Restores the previous state when dropped
+#[must_use]
+pub(crate) struct StateGuard<'a> {
+    previous: RenderState,
+    cache: &'a StateCache,
+}
+
+impl Drop for StateGuard<'_> {
+    fn drop(&mut self) {
+        self.cache.apply(&self.previous);
+    }
+}
@@ -1,54 +1,264 @@
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
 use glow::*;
 
+use super::barrier::{BindingPoint, HazardTracker, MemoryBarrier, Resource};
+use super::binding::BindingStack;
+use super::gl_ext::GlExt;
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::sampler::Sampler;
+
+/// How a texture is stored on the GPU. The data uploaded into it is described separately by a `PixelType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TextureFormat {
+    R8,
+    RG8,
+    RGB8,
+    RGBA8,
+    /// sRGB color, sampled as linear
+    SRGB8,
+    /// sRGB color with linear alpha, sampled as linear
+    SRGB8A8,
+    R16F,
+    RG16F,
+    RGBA16F,
+    R32F,
+    RG32F,
+    RGB32F,
+    RGBA32F,
+    /// Unsigned integer, sampled with `usampler2D`. Supports image atomics
+    R32UI,
+    /// Signed integer, sampled with `isampler2D`. Supports image atomics
+    R32I,
+    /// Packed unsigned floats, half the size of `RGBA16F` for HDR color without alpha
+    R11G11B10F,
+    Depth16,
+    Depth24,
+    Depth32F,
+    Depth24Stencil8,
+    Depth32FStencil8,
+    #[deprecated(note = "Use `TextureFormat::R32F`")]
     R,
+    #[deprecated(note = "Use `TextureFormat::RG32F`")]
     RG,
+    #[deprecated(note = "Use `TextureFormat::RGB32F`")]
     RGB,
+    #[deprecated(note = "Use `TextureFormat::RGBA32F`")]
     RGBA,
 }
 
 impl TextureFormat {
-    fn to_gl_format(&self) -> u32 {
+    /// Maps the deprecated float aliases to their replacements
+    #[allow(deprecated)]
+    fn canonical(&self) -> Self {
         match self {
-            Self::RGB => RGB,
-            Self::RGBA => RGBA,
-            _ => unimplemented!(),
+            Self::R => Self::R32F,
+            Self::RG => Self::RG32F,
+            Self::RGB => Self::RGB32F,
+            Self::RGBA => Self::RGBA32F,
+            other => *other,
+        }
+    }
+
+    /// Channel layout of uploaded pixels
+    pub(crate) fn to_gl_format(self) -> u32 {
+        match self.canonical() {
+            Self::R8 | Self::R16F | Self::R32F => RED,
+            Self::RG8 | Self::RG16F | Self::RG32F => RG,
+            Self::RGB8 | Self::SRGB8 | Self::RGB32F | Self::R11G11B10F => RGB,
+            Self::RGBA8 | Self::SRGB8A8 | Self::RGBA16F | Self::RGBA32F => RGBA,
+            Self::R32UI | Self::R32I => RED_INTEGER,
+            Self::Depth16 | Self::Depth24 | Self::Depth32F => DEPTH_COMPONENT,
+            Self::Depth24Stencil8 | Self::Depth32FStencil8 => DEPTH_STENCIL,
+            _ => unreachable!(),
+        }
+    }
+
+    pub(crate) fn to_gl_internal_format(self) -> i32 {
+        (match self.canonical() {
+            Self::R8 => R8,
+            Self::RG8 => RG8,
+            Self::RGB8 => RGB8,
+            Self::RGBA8 => RGBA8,
+            Self::SRGB8 => SRGB8,
+            Self::SRGB8A8 => SRGB8_ALPHA8,
+            Self::R16F => R16F,
+            Self::RG16F => RG16F,
+            Self::RGBA16F => RGBA16F,
+            Self::R32F => R32F,
+            Self::RG32F => RG32F,
+            Self::RGB32F => RGB32F,
+            Self::RGBA32F => RGBA32F,
+            Self::R32UI => R32UI,
+            Self::R32I => R32I,
+            Self::R11G11B10F => R11F_G11F_B10F,
+            Self::Depth16 => DEPTH_COMPONENT16,
+            Self::Depth24 => DEPTH_COMPONENT24,
+            Self::Depth32F => DEPTH_COMPONENT32F,
+            Self::Depth24Stencil8 => DEPTH24_STENCIL8,
+            Self::Depth32FStencil8 => DEPTH32F_STENCIL8,
+            _ => unreachable!(),
+        }) as i32
+    }
+
+    /// What `Texture::new` expects its pixels in
+    pub fn pixel_type(&self) -> PixelType {
+        match self.canonical() {
+            Self::R8 | Self::RG8 | Self::RGB8 | Self::RGBA8 | Self::SRGB8 | Self::SRGB8A8 => PixelType::U8,
+            Self::R16F | Self::RG16F | Self::RGBA16F => PixelType::F32,
+            Self::R32F | Self::RG32F | Self::RGB32F | Self::RGBA32F | Self::R11G11B10F => PixelType::F32,
+            Self::R32UI => PixelType::U32,
+            Self::R32I => PixelType::I32,
+            Self::Depth16 => PixelType::U16,
+            Self::Depth24 => PixelType::U32,
+            Self::Depth32F => PixelType::F32,
+            Self::Depth24Stencil8 => PixelType::PackedDepth24Stencil8,
+            Self::Depth32FStencil8 => PixelType::PackedDepth32FStencil8,
+            _ => unreachable!(),
         }
     }
 
-    fn to_gl_internal_format(&self) -> i32 {
+    /// Number of values per pixel in uploaded data
+    pub fn channels(&self) -> usize {
+        match self.to_gl_format() {
+            RG | DEPTH_STENCIL => 2,
+            RGB => 3,
+            RGBA => 4,
+            _ => 1,
+        }
+    }
+
+    /// Bytes per pixel on the GPU, as far as the driver lets us know
+    pub fn size(&self) -> usize {
+        match self.canonical() {
+            Self::R8 => 1,
+            Self::RG8 | Self::R16F | Self::Depth16 => 2,
+            Self::RGB8 | Self::RGBA8 | Self::SRGB8 | Self::SRGB8A8 | Self::RG16F | Self::R32F | Self::R32UI | Self::R32I | Self::R11G11B10F => 4,
+            Self::Depth24 | Self::Depth32F | Self::Depth24Stencil8 => 4,
+            Self::RGBA16F | Self::RG32F | Self::Depth32FStencil8 => 8,
+            Self::RGB32F => 12,
+            Self::RGBA32F => 16,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Integer textures can't be filtered or mipmapped
+    pub fn is_integer(&self) -> bool {
+        matches!(self.canonical(), Self::R32UI | Self::R32I)
+    }
+
+    pub fn is_depth(&self) -> bool {
+        matches!(self.to_gl_format(), DEPTH_COMPONENT | DEPTH_STENCIL)
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        matches!(self.canonical(), Self::SRGB8 | Self::SRGB8A8)
+    }
+}
+
+/// Type of every value in uploaded pixel data, in the channel order of the `TextureFormat`.
+/// GL converts it to the format of the texture, for example `F32` into `RGBA16F`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    /// Half floats, as their `u16` bits
+    F16,
+    F32,
+    /// One `u32` per pixel, depth in the upper 24 bits
+    PackedDepth24Stencil8,
+    /// 8 bytes per pixel, an `f32` depth followed by a `u32` with stencil in the lowest 8 bits
+    PackedDepth32FStencil8,
+    /// One `u32` per pixel, for `R11G11B10F`
+    PackedR11G11B10F,
+}
+
+impl PixelType {
+    pub(crate) fn to_gl(self) -> u32 {
         match self {
-            Self::RGB => RGB32F as i32,
-            Self::RGBA => RGBA32F as i32,
-            _ => unimplemented!(),
+            Self::U8 => UNSIGNED_BYTE,
+            Self::I8 => BYTE,
+            Self::U16 => UNSIGNED_SHORT,
+            Self::I16 => SHORT,
+            Self::U32 => UNSIGNED_INT,
+            Self::I32 => INT,
+            Self::F16 => HALF_FLOAT,
+            Self::F32 => FLOAT,
+            Self::PackedDepth24Stencil8 => UNSIGNED_INT_24_8,
+            Self::PackedDepth32FStencil8 => FLOAT_32_UNSIGNED_INT_24_8_REV,
+            Self::PackedR11G11B10F => UNSIGNED_INT_10F_11F_11F_REV,
         }
     }
 
-    fn to_gl_repr(&self) -> u32 {
+    fn is_packed(&self) -> bool {
+        matches!(self, Self::PackedDepth24Stencil8 | Self::PackedDepth32FStencil8 | Self::PackedR11G11B10F)
+    }
+
+    /// Bytes per pixel of `format` in this type
+    pub fn pixel_size(&self, format: TextureFormat) -> usize {
         match self {
-            Self::RGB => FLOAT,
-            Self::RGBA => FLOAT,
-            _ => unimplemented!(),
+            Self::U8 | Self::I8 => format.channels(),
+            Self::U16 | Self::I16 | Self::F16 => 2 * format.channels(),
+            Self::U32 | Self::I32 | Self::F32 => 4 * format.channels(),
+            Self::PackedDepth24Stencil8 | Self::PackedR11G11B10F => 4,
+            Self::PackedDepth32FStencil8 => 8,
         }
     }
+
+    /// Panics if GL can't upload this type into `format`
+    pub(crate) fn check(&self, format: TextureFormat) {
+        let valid = match format.to_gl_format() {
+            DEPTH_STENCIL => matches!((format.canonical(), self), (TextureFormat::Depth24Stencil8, Self::PackedDepth24Stencil8) | (TextureFormat::Depth32FStencil8, Self::PackedDepth32FStencil8)),
+            RED_INTEGER => !self.is_packed() && !matches!(self, Self::F16 | Self::F32),
+            RGB if format == TextureFormat::R11G11B10F => !matches!(self, Self::PackedDepth24Stencil8 | Self::PackedDepth32FStencil8),
+            _ => !self.is_packed(),
+        };
+        if !valid {
+            panic!("Cannot upload {:?} pixels into a {:?} texture!", self, format);
+        }
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Plain data `read_pixels` can fill with raw bytes: `u8`, `i8`, `u16`, `i16`, `u32`, `i32`, `f32` and arrays of them.
+/// Half floats come back as `u16` bits
+pub trait PixelData: sealed::Sealed + Copy {}
+
+macro_rules! pixel_data {
+    ($($t:ty),*) => {
+        $(
+            impl sealed::Sealed for $t {}
+            impl PixelData for $t {}
+        )*
+    };
 }
 
+pixel_data!(u8, i8, u16, i16, u32, i32, f32);
+
+impl<T: PixelData, const N: usize> sealed::Sealed for [T; N] {}
+impl<T: PixelData, const N: usize> PixelData for [T; N] {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TextureFiltering {
     Linear,
     Nearest,
 }
 
 impl TextureFiltering {
-    fn to_gl(&self) -> i32 {
+    fn to_gl(self) -> i32 {
         match self {
             Self::Linear => LINEAR as i32,
             Self::Nearest => NEAREST as i32,
         }
     }
 
-    fn to_gl_mipmap(&self) -> i32 {
+    fn to_gl_mipmap(self) -> i32 {
         match self {
             Self::Linear => LINEAR_MIPMAP_LINEAR as i32,
             Self::Nearest => NEAREST_MIPMAP_NEAREST as i32,
@@ -56,6 +266,7 @@ impl TextureFiltering {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TextureSettings {
     pub width: usize,
     pub height: usize,
@@ -65,109 +276,383 @@ pub struct TextureSettings {
 }
 
 pub struct Texture {
-    tex: glow::Texture,
-    settings: TextureSettings,
+    inner: TextureObject,
+}
+
+impl Texture {
+    /// `pixels` are in the default `PixelType` of the format, see `TextureFormat::pixel_type`
+    pub fn new(renderer: &super::Renderer, settings: TextureSettings, pixels: Option<&[u8]>) -> Self {
+        Self::with_pixel_type(renderer, settings, settings.format.pixel_type(), pixels)
+    }
+
+    /// Like `new`, with `pixels` made of `pixel_type` values.
+    /// Panics if the data doesn't cover the whole texture, or can't be converted to the format!
+    pub fn with_pixel_type(renderer: &super::Renderer, settings: TextureSettings, pixel_type: PixelType, pixels: Option<&[u8]>) -> Self {
+        Self {
+            inner: TextureObject::new(renderer, TEXTURE_2D, settings, 1, pixel_type, pixels, "Texture"),
+        }
+    }
+
+    /// Replaces the label shown in the resource registry and graphics debuggers
+    pub fn with_label(self, label: &str) -> Self {
+        self.inner.set_label(label);
+        self
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.inner.settings.width, self.inner.settings.height)
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.inner.settings.format
+    }
+
+    /// Recreates the texture, `pixels` are in the `PixelType` it was created with
+    pub fn resize(&mut self, size: (usize, usize), pixels: Option<&[u8]>) {
+        self.inner.resize(size.0, size.1, 1, pixels);
+    }
+
+    /// Number of mip levels, including the base level
+    pub fn mip_levels(&self) -> usize {
+        self.inner.mip_levels()
+    }
+
+    /// Size of mip level `level`
+    pub fn level_size(&self, level: usize) -> (usize, usize) {
+        let [width, height, _] = self.inner.level_size(level);
+        (width, height)
+    }
+
+    /// Replaces a rectangle of mip level `level`, `pixels` are in the `PixelType` the texture was created with.
+    /// Panics if the rectangle doesn't fit in the level, or `pixels` doesn't cover it!
+    pub fn write_region(&self, x: usize, y: usize, width: usize, height: usize, level: usize, pixels: &[u8]) {
+        self.inner.write_region([x, y, 0], [width, height, 1], level, pixels);
+    }
+
+    /// Replaces a whole mip level, for mips that aren't simply downscaled (or to skip `generate_mipmaps`)
+    pub fn write_level(&self, level: usize, pixels: &[u8]) {
+        self.inner.write_level(level, pixels);
+    }
+
+    /// Recomputes every mip level from the base level, e.g. after `write_region` or a compute shader wrote to it.
+    /// Panics if the texture was created without mipmaps!
+    pub fn generate_mipmaps(&self) {
+        self.inner.generate_mipmaps();
+    }
+
+    /// Reads mip level `level` back to the CPU, in the `PixelType` the texture was created with.
+    /// `T` is any `PixelData` that tiles a pixel, like `u8` or `[u8; 4]` for `RGBA8` and `f32` for the float formats.
+    /// This waits for the GPU to finish everything that writes to the texture!
+    pub fn read_pixels<T: PixelData>(&self, level: usize) -> Vec<T> {
+        self.inner.read_pixels(level)
+    }
+
+    pub fn bind_img(&self, location: u32, write: bool, read: bool) {
+        self.inner.bind_img(location, None, write, read);
+    }
+
+    pub fn unbind_img(&self, location: u32) {
+        self.inner.unbind_img(location);
+    }
+
+    /// Runs a closure while the texture is bound to texture unit `location`.
+    /// Whatever was bound to that unit before is bound again afterwards.
+    pub fn while_bound<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, f: F) -> Result<(), super::RenderError> {
+        self.inner.while_bound(location, f)
+    }
+
+    /// Like `while_bound`, sampled through `sampler` instead of the texture's own filtering
+    pub fn while_bound_with<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, sampler: &Sampler, f: F) -> Result<(), super::RenderError> {
+        sampler.while_bound(location, || self.inner.while_bound(location, f))
+    }
+
+    /// Runs a closure while the texture is bound as an image texture (mainly for compute shaders)
+    pub fn while_bound_img<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, write: bool, read: bool, f: F) -> Result<(), super::RenderError> {
+        self.inner.while_bound_img(location, None, write, read, f)
+    }
+}
+
+/// The GL texture behind `Texture` and the layered textures, `target` decides which one it is
+pub(crate) struct TextureObject {
+    pub(crate) tex: glow::Texture,
+    target: u32,
+    pub(crate) settings: TextureSettings,
+    /// Depth, layer count or 6 faces, 1 for 2D textures
+    pub(crate) depth: usize,
+    /// What `resize` expects its pixels in
+    pixel_type: PixelType,
     gl: Arc<Context>,
-    shader_bound: Arc<AtomicBool>,
+    ext: Arc<GlExt>,
+    tracker: Arc<HazardTracker>,
+    bindings: Arc<BindingStack>,
+    resources: Arc<ResourceRegistry>,
 }
 
-impl Drop for Texture {
+impl Drop for TextureObject {
     fn drop(&mut self) {
+        self.tracker.forget(Resource::texture(self.tex));
+        self.resources.unregister(ResourceKind::Texture, self.tex.0.get());
         unsafe {
             self.gl.delete_texture(self.tex);
         }
     }
 }
 
-impl Texture {
-    pub fn new(renderer: &super::Renderer, settings: TextureSettings, pixels: Option<&[u8]>) -> Self {
+impl TextureObject {
+    pub(crate) fn new(renderer: &super::Renderer, target: u32, settings: TextureSettings, depth: usize, pixel_type: PixelType, pixels: Option<&[u8]>, name: &str) -> Self {
         let gl = renderer.gl.clone();
-        let tex = new_tex(gl.clone(), &settings, pixels);
+        let tex = new_tex(gl.clone(), &renderer.bindings, target, &settings, depth, pixel_type, pixels);
         super::gl_error(&gl, "texture::new");
+        let label = if target == TEXTURE_2D {
+            format!("{} {}x{} {:?}", name, settings.width, settings.height, settings.format)
+        } else {
+            format!("{} {}x{}x{} {:?}", name, settings.width, settings.height, depth, settings.format)
+        };
+        renderer.resources.register(ResourceKind::Texture, tex.0.get(), byte_size(&settings, target, depth), &label);
         Self {
             tex,
+            target,
             settings,
+            depth,
+            pixel_type,
             gl,
-            shader_bound: renderer.shader_bound.clone(),
+            ext: renderer.ext.clone(),
+            tracker: renderer.tracker.clone(),
+            bindings: renderer.bindings.clone(),
+            resources: renderer.resources.clone(),
         }
     }
 
-    pub fn size(&self) -> (usize, usize) {
-        (self.settings.width, self.settings.height)
+    pub(crate) fn set_label(&self, label: &str) {
+        self.resources.set_label(ResourceKind::Texture, self.tex.0.get(), label);
     }
 
-    pub fn resize(&mut self, size: (usize, usize), pixels: Option<&[u8]>) {
-        self.settings.width = size.0;
-        self.settings.height = size.1;
-        let tex = new_tex(self.gl.clone(), &self.settings, pixels);
+    pub(crate) fn mip_levels(&self) -> usize {
+        mip_levels(&self.settings, mip_depth(self.target, self.depth))
+    }
+
+    pub(crate) fn level_size(&self, level: usize) -> [usize; 3] {
+        let depth = if self.target == TEXTURE_3D { (self.depth >> level).max(1) } else { self.depth };
+        [(self.settings.width >> level).max(1), (self.settings.height >> level).max(1), depth]
+    }
+
+    pub(crate) fn resize(&mut self, width: usize, height: usize, depth: usize, pixels: Option<&[u8]>) {
+        self.settings.width = width;
+        self.settings.height = height;
+        self.depth = depth;
+        let tex = new_tex(self.gl.clone(), &self.bindings, self.target, &self.settings, depth, self.pixel_type, pixels);
+        self.tracker.forget(Resource::texture(self.tex));
+        self.resources.replace(ResourceKind::Texture, self.tex.0.get(), tex.0.get(), byte_size(&self.settings, self.target, depth));
+        unsafe {
+            self.gl.delete_texture(self.tex);
+        }
         self.tex = tex;
     }
 
-    fn bind_tex(&self, location: u32) {
+    fn check_level(&self, level: usize) {
+        if level >= self.mip_levels() {
+            panic!("Texture has {} mip levels, level {} doesn't exist!", self.mip_levels(), level);
+        }
+    }
+
+    /// `z` is the depth, array layer or cubemap face, 0 for 2D textures
+    pub(crate) fn write_region(&self, offset: [usize; 3], size: [usize; 3], level: usize, pixels: &[u8]) {
+        self.check_level(level);
+        let level_size = self.level_size(level);
+        if (0..3).any(|i| offset[i] + size[i] > level_size[i]) {
+            panic!("Region {:?} at {:?} doesn't fit in the {:?} mip level {}!", size, offset, level_size, level);
+        }
+        let expected = size[0] * size[1] * size[2] * self.pixel_type.pixel_size(self.settings.format);
+        if pixels.len() != expected {
+            panic!("Expected {} bytes of {:?} pixels for a {:?} region, got {}!", expected, self.pixel_type, size, pixels.len());
+        }
+        self.tracker.read(Resource::texture(self.tex), MemoryBarrier::TEXTURE_UPDATE);
+        let (format, ty) = (self.settings.format.to_gl_format(), self.pixel_type.to_gl());
+        unsafe {
+            self.gl.pixel_store_i32(UNPACK_ALIGNMENT, 1);
+            if self.target == TEXTURE_2D {
+                self.ext.texture_sub_image_2d(self.tex, level as i32, offset[0] as i32, offset[1] as i32, size[0] as i32, size[1] as i32, format, ty, pixels);
+            } else {
+                self.gl.texture_sub_image_3d(
+                    self.tex, level as i32,
+                    offset[0] as i32, offset[1] as i32, offset[2] as i32,
+                    size[0] as i32, size[1] as i32, size[2] as i32,
+                    format, ty, PixelUnpackData::Slice(pixels),
+                );
+            }
+            self.gl.pixel_store_i32(UNPACK_ALIGNMENT, 4);
+        }
+        super::gl_error(&self.gl, "texture::write_region");
+    }
+
+    /// Every layer of mip level `level`
+    pub(crate) fn write_level(&self, level: usize, pixels: &[u8]) {
+        self.check_level(level);
+        self.write_region([0; 3], self.level_size(level), level, pixels);
+    }
+
+    pub(crate) fn generate_mipmaps(&self) {
+        if !self.settings.mipmap {
+            panic!("Texture was created without mipmaps!");
+        }
+        self.tracker.read(Resource::texture(self.tex), MemoryBarrier::TEXTURE_UPDATE);
         unsafe {
-            self.gl.active_texture(glow::TEXTURE0 + location);
-            self.gl.bind_texture(TEXTURE_2D, Some(self.tex));
+            self.gl.generate_texture_mipmap(self.tex);
         }
     }
 
-    fn unbind_tex(&self) {
+    pub(crate) fn read_pixels<T: PixelData>(&self, level: usize) -> Vec<T> {
+        self.check_level(level);
+        let [width, height, depth] = self.level_size(level);
+        let bytes = width * height * depth * self.pixel_type.pixel_size(self.settings.format);
+        let t_size = std::mem::size_of::<T>();
+        if t_size == 0 || !bytes.is_multiple_of(t_size) {
+            panic!("{:?} pixels of a {:?} texture can't be read as `{}`!", self.pixel_type, self.settings.format, std::any::type_name::<T>());
+        }
+        // Every bit pattern is a valid `PixelData`, so zeroes are too and so is whatever GL writes
+        let mut data = vec![unsafe { std::mem::zeroed::<T>() }; bytes / t_size];
+        self.tracker.read(Resource::texture(self.tex), MemoryBarrier::TEXTURE_UPDATE);
         unsafe {
-            self.gl.bind_texture(TEXTURE_2D, None);
+            let data_raw: &mut [u8] = std::slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, bytes);
+            self.gl.pixel_store_i32(PACK_ALIGNMENT, 1);
+            self.ext.get_texture_image(self.tex, level as i32, self.settings.format.to_gl_format(), self.pixel_type.to_gl(), data_raw);
+            self.gl.pixel_store_i32(PACK_ALIGNMENT, 4);
         }
+        super::gl_error(&self.gl, "texture::read_pixels");
+        data
     }
 
-    pub fn bind_img(&self, location: u32, write: bool, read: bool) {
-        self.bind_tex(location);
-        let access = if write && read { glow::READ_WRITE } else if write { glow::WRITE_ONLY } else { glow::READ_ONLY };
+    /// `layer` binds a single layer or face of a layered texture, `None` binds all of them
+    pub(crate) fn bind_img(&self, location: u32, layer: Option<usize>, write: bool, read: bool) {
+        if let Some(layer) = layer {
+            if layer >= self.depth {
+                panic!("Texture has {} layers, layer {} doesn't exist!", self.depth, layer);
+            }
+        }
+        self.tracker.bind_read(BindingPoint::Image(location), Resource::texture(self.tex), MemoryBarrier::SHADER_IMAGE_ACCESS);
+        let access = if write && read { READ_WRITE } else if write { WRITE_ONLY } else { READ_ONLY };
+        let layered = layer.is_none() && self.target != TEXTURE_2D;
         unsafe {
-            self.gl.bind_image_texture(location, self.tex, 0, false, 0, access, self.settings.format.to_gl_internal_format() as u32);
+            self.gl.bind_image_texture(location, self.tex, 0, layered, layer.unwrap_or(0) as i32, access, self.settings.format.to_gl_internal_format() as u32);
+        }
+        if write {
+            self.tracker.bind_write(BindingPoint::Image(location), Resource::texture(self.tex));
+        } else {
+            self.tracker.unbind_write(BindingPoint::Image(location));
         }
     }
 
-    pub fn unbind_img(&self, _location: u32) {
-        self.unbind_tex();
-        // unsafe {
-        //     self.gl.bind_image_texture(location, self.tex, 0, false, 0, glow::READ_ONLY, self.settings.format.to_gl_internal_format() as u32);
-        // }
+    pub(crate) fn unbind_img(&self, location: u32) {
+        self.tracker.unbind_write(BindingPoint::Image(location));
+        self.tracker.unbind_read(BindingPoint::Image(location), None);
     }
 
-    /// Runs a closure while the texture is bound
-    pub fn while_bound<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, f: F) -> Result<(), super::RenderError> {
-        if self.shader_bound.load(Ordering::Acquire) == false {
+    pub(crate) fn while_bound<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, f: F) -> Result<(), super::RenderError> {
+        if !self.bindings.program_bound() {
             panic!("No shader bound, but you are trying to bind a texture!");
         }
-        self.bind_tex(location);
-        f()?;
-        self.unbind_tex();
-        Ok(())
+        let previous = self.tracker.bind_read(BindingPoint::Texture(location), Resource::texture(self.tex), MemoryBarrier::TEXTURE_FETCH);
+        let _guard = self.bindings.push_texture(location, self.target, self.tex);
+        let result = f();
+        self.tracker.unbind_read(BindingPoint::Texture(location), previous);
+        result
     }
 
-    /// Runs a closure while the texture is bound as an image texture (mainly for compute shaders)
-    pub fn while_bound_img<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, write: bool, read: bool, f: F) -> Result<(), super::RenderError> {
-        if self.shader_bound.load(Ordering::Acquire) == false {
+    pub(crate) fn while_bound_img<F: FnOnce() -> Result<(), super::RenderError>>(&self, location: u32, layer: Option<usize>, write: bool, read: bool, f: F) -> Result<(), super::RenderError> {
+        if !self.bindings.program_bound() {
             panic!("No shader bound, but you are trying to bind a texture!");
         }
-        self.bind_img(location, write, read);
-        f()?;
+        self.bind_img(location, layer, write, read);
+        let result = f();
         self.unbind_img(location);
-        Ok(())
+        result
+    }
+}
+
+/// Anything `UniformInterface::bind_image` can bind
+pub trait ImageTexture {
+    fn bind_img(&self, location: u32, write: bool, read: bool);
+
+    /// The GL texture, so the shader can track it while it's bound.
+    ///
+    /// # Safety
+    /// The handle is only valid while `self` is alive, and must not be deleted or have its storage changed.
+    unsafe fn tex(&self) -> NativeTexture;
+}
+
+impl ImageTexture for Texture {
+    fn bind_img(&self, location: u32, write: bool, read: bool) {
+        Texture::bind_img(self, location, write, read)
+    }
+
+    unsafe fn tex(&self) -> NativeTexture {
+        self.inner.tex
     }
 }
 
-fn new_tex(gl: Arc<Context>, settings: &TextureSettings, pixels: Option<&[u8]>) -> glow::Texture {
-    let tex = unsafe {
-        let tex = gl.create_texture().map_err(|e| error!("{}", e)).expect("Failed to create texture!");
-        gl.bind_texture(TEXTURE_2D, Some(tex));
-        gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_BASE_LEVEL, 0);
-        gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MAX_LEVEL, 5);
-        gl.tex_parameter_f32(TEXTURE_2D, TEXTURE_LOD_BIAS, -1.8);
-        gl.tex_image_2d(TEXTURE_2D, 0, settings.format.to_gl_internal_format(), settings.width as i32, settings.height as i32, 0, settings.format.to_gl_format(), settings.format.to_gl_repr(), pixels);
+/// Only 3D textures get smaller in depth, array layers and cubemap faces stay
+pub(crate) fn mip_depth(target: u32, depth: usize) -> usize {
+    if target == TEXTURE_3D { depth } else { 1 }
+}
+
+/// The full chain down to 1x1 when mipmapped
+pub(crate) fn mip_levels(settings: &TextureSettings, mip_depth: usize) -> usize {
+    if settings.mipmap {
+        (usize::BITS - settings.width.max(settings.height).max(mip_depth).max(1).leading_zeros()) as usize
+    } else {
+        1
+    }
+}
+
+/// Mipmaps add a third on top of the base level, or a seventh for 3D textures
+pub(crate) fn byte_size(settings: &TextureSettings, target: u32, depth: usize) -> usize {
+    let base = settings.width * settings.height * depth * settings.format.size();
+    match settings.mipmap {
+        false => base,
+        true if target == TEXTURE_3D => base * 8 / 7,
+        true => base * 4 / 3,
+    }
+}
+
+/// Creates a texture of `target`, with `depth` layers for 3D textures, arrays and cubemaps (1 otherwise).
+/// Cubemap `pixels` hold the six faces one after another.
+pub(crate) fn new_tex(gl: Arc<Context>, bindings: &Arc<BindingStack>, target: u32, settings: &TextureSettings, depth: usize, pixel_type: PixelType, pixels: Option<&[u8]>) -> glow::Texture {
+    pixel_type.check(settings.format);
+    if settings.format.is_integer() && (settings.mipmap || settings.filtering != TextureFiltering::Nearest) {
+        panic!("Integer textures can only use nearest filtering without mipmaps!");
+    }
+    if let Some(pixels) = pixels {
+        let expected = settings.width * settings.height * depth * pixel_type.pixel_size(settings.format);
+        if pixels.len() != expected {
+            panic!("Expected {} bytes of {:?} pixels for a {}x{}x{} {:?} texture, got {}!", expected, pixel_type, settings.width, settings.height, depth, settings.format, pixels.len());
+        }
+    }
+    let tex = unsafe { gl.create_texture().map_err(|e| error!("{}", e)).expect("Failed to create texture!") };
+    // Through the binding stack, so whatever a surrounding `while_bound` has on unit 0 gets bound again
+    let _guard = bindings.push_texture(0, target, tex);
+    unsafe {
+        gl.tex_parameter_i32(target, TEXTURE_BASE_LEVEL, 0);
+        gl.tex_parameter_i32(target, TEXTURE_MAX_LEVEL, mip_levels(settings, mip_depth(target, depth)) as i32 - 1);
+        // Rows of 8-bit RGB data aren't 4-byte aligned
+        gl.pixel_store_i32(UNPACK_ALIGNMENT, 1);
+        let (internal_format, format, ty) = (settings.format.to_gl_internal_format(), settings.format.to_gl_format(), pixel_type.to_gl());
+        let (width, height) = (settings.width as i32, settings.height as i32);
+        match target {
+            TEXTURE_2D => gl.tex_image_2d(target, 0, internal_format, width, height, 0, format, ty, pixels),
+            TEXTURE_CUBE_MAP => {
+                let face_size = settings.width * settings.height * pixel_type.pixel_size(settings.format);
+                for face in 0..6 {
+                    let face_pixels = pixels.map(|p| &p[face * face_size..(face + 1) * face_size]);
+                    gl.tex_image_2d(TEXTURE_CUBE_MAP_POSITIVE_X + face as u32, 0, internal_format, width, height, 0, format, ty, face_pixels);
+                }
+            },
+            _ => gl.tex_image_3d(target, 0, internal_format, width, height, depth as i32, 0, format, ty, pixels),
+        }
+        gl.pixel_store_i32(UNPACK_ALIGNMENT, 4);
         if settings.mipmap { gl.generate_texture_mipmap(tex); }
-        // Regular filtering
-        gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MIN_FILTER, if settings.mipmap { settings.filtering.to_gl_mipmap() } else { settings.filtering.to_gl() });
-        gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MAG_FILTER, settings.filtering.to_gl());
-        gl.bind_texture(TEXTURE_2D, None);
-        tex
-    };
+        // Used when no `Sampler` is bound alongside the texture
+        gl.tex_parameter_i32(target, TEXTURE_MIN_FILTER, if settings.mipmap { settings.filtering.to_gl_mipmap() } else { settings.filtering.to_gl() });
+        gl.tex_parameter_i32(target, TEXTURE_MAG_FILTER, settings.filtering.to_gl());
+    }
     tex
 }
@@ -0,0 +1,195 @@
+//! Creating textures from image files (PNG, JPEG, TGA, BMP, HDR and EXR) through the `image` crate.
+//! 8-bit images keep their 8-bit formats, 16-bit ones become half floats and HDR/EXR stay 32-bit floats.
+use std::path::Path;
+
+use image::DynamicImage;
+
+use super::texture::{PixelType, Texture, TextureFiltering, TextureFormat, TextureSettings};
+use super::vertex::slice_as_bytes;
+
+pub use image::ImageError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageOptions {
+    /// Treat RGB(A) as sRGB color: 8-bit images use sRGB formats, 16-bit ones are decoded to linear on load.
+    /// Grayscale is always loaded as is, there are no one or two channel sRGB formats.
+    /// Turn this off for normal maps and other data
+    pub srgb: bool,
+    /// Put the first row of the file at the top (v = 1), like the built-in primitives expect
+    pub flip_vertical: bool,
+    /// Multiply color by alpha. For sRGB images this happens in linear space
+    pub premultiply_alpha: bool,
+    pub mipmap: bool,
+    pub filtering: TextureFiltering,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            srgb: true,
+            flip_vertical: true,
+            premultiply_alpha: false,
+            mipmap: true,
+            filtering: TextureFiltering::Linear,
+        }
+    }
+}
+
+impl Texture {
+    /// Decodes an image file, guessing the format from its extension and contents
+    pub fn from_path<P: AsRef<Path>>(renderer: &super::Renderer, path: P, options: ImageOptions) -> Result<Self, ImageError> {
+        let path = path.as_ref();
+        let image = image::ImageReader::open(path)?.with_guessed_format()?.decode()?;
+        Ok(Self::from_image(renderer, image, options).with_label(&path.display().to_string()))
+    }
+
+    /// Decodes an image file that's already in memory
+    pub fn from_memory(renderer: &super::Renderer, data: &[u8], options: ImageOptions) -> Result<Self, ImageError> {
+        Ok(Self::from_image(renderer, image::load_from_memory(data)?, options))
+    }
+
+    pub fn from_image(renderer: &super::Renderer, image: DynamicImage, options: ImageOptions) -> Self {
+        let image = if options.flip_vertical { image.flipv() } else { image };
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let settings = |format| TextureSettings {
+            width,
+            height,
+            format,
+            filtering: options.filtering,
+            mipmap: options.mipmap,
+        };
+        match image {
+            DynamicImage::ImageLuma8(image) => Texture::new(renderer, settings(TextureFormat::R8), Some(image.as_raw())),
+            DynamicImage::ImageLumaA8(image) => {
+                let mut pixels = image.into_raw();
+                if options.premultiply_alpha {
+                    premultiply_u8(&mut pixels, 2, false);
+                }
+                Texture::new(renderer, settings(TextureFormat::RG8), Some(&pixels))
+            },
+            DynamicImage::ImageRgb8(image) => {
+                let format = if options.srgb { TextureFormat::SRGB8 } else { TextureFormat::RGB8 };
+                Texture::new(renderer, settings(format), Some(image.as_raw()))
+            },
+            DynamicImage::ImageRgba8(image) => {
+                let format = if options.srgb { TextureFormat::SRGB8A8 } else { TextureFormat::RGBA8 };
+                let mut pixels = image.into_raw();
+                if options.premultiply_alpha {
+                    premultiply_u8(&mut pixels, 4, options.srgb);
+                }
+                Texture::new(renderer, settings(format), Some(&pixels))
+            },
+            // Like 8-bit grayscale, never decoded from sRGB
+            DynamicImage::ImageLuma16(image) => {
+                let pixels = image.as_raw().iter().map(|&v| v as f32 / 65535.0).collect::<Vec<f32>>();
+                Texture::with_pixel_type(renderer, settings(TextureFormat::R16F), PixelType::F32, Some(slice_as_bytes(&pixels)))
+            },
+            DynamicImage::ImageLumaA16(image) => {
+                let mut pixels = image.as_raw().iter().map(|&v| v as f32 / 65535.0).collect::<Vec<f32>>();
+                if options.premultiply_alpha {
+                    premultiply_f32(&mut pixels, 2);
+                }
+                Texture::with_pixel_type(renderer, settings(TextureFormat::RG16F), PixelType::F32, Some(slice_as_bytes(&pixels)))
+            },
+            DynamicImage::ImageRgb32F(image) => {
+                Texture::new(renderer, settings(TextureFormat::RGB32F), Some(slice_as_bytes(image.as_raw())))
+            },
+            DynamicImage::ImageRgba32F(image) => {
+                let mut pixels = image.into_raw();
+                if options.premultiply_alpha {
+                    premultiply_f32(&mut pixels, 4);
+                }
+                Texture::new(renderer, settings(TextureFormat::RGBA32F), Some(slice_as_bytes(&pixels)))
+            },
+            // 16-bit color and anything else
+            image => {
+                let mut pixels = image.into_rgba32f().into_raw();
+                // Float formats are always linear, so sRGB color is decoded here
+                if options.srgb {
+                    for pixel in pixels.chunks_exact_mut(4) {
+                        for c in &mut pixel[..3] {
+                            *c = srgb_to_linear(*c);
+                        }
+                    }
+                }
+                if options.premultiply_alpha {
+                    premultiply_f32(&mut pixels, 4);
+                }
+                Texture::with_pixel_type(renderer, settings(TextureFormat::RGBA16F), PixelType::F32, Some(slice_as_bytes(&pixels)))
+            },
+        }
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+/// Alpha is the last of `channels`
+fn premultiply_u8(pixels: &mut [u8], channels: usize, srgb: bool) {
+    for pixel in pixels.chunks_exact_mut(channels) {
+        let alpha = pixel[channels - 1] as f32 / 255.0;
+        for c in &mut pixel[..channels - 1] {
+            let value = *c as f32 / 255.0;
+            let value = if srgb { linear_to_srgb(srgb_to_linear(value) * alpha) } else { value * alpha };
+            *c = (value * 255.0).round() as u8;
+        }
+    }
+}
+
+fn premultiply_f32(pixels: &mut [f32], channels: usize) {
+    for pixel in pixels.chunks_exact_mut(channels) {
+        let alpha = pixel[channels - 1];
+        for c in &mut pixel[..channels - 1] {
+            *c *= alpha;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_round_trip() {
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-6);
+        assert!((srgb_to_linear(0.5) - 0.214).abs() < 1e-3);
+        for i in 0..=255 {
+            let c = i as f32 / 255.0;
+            assert!((linear_to_srgb(srgb_to_linear(c)) - c).abs() < 1e-5, "{} doesn't survive the round trip", c);
+        }
+    }
+
+    #[test]
+    fn premultiply_bytes() {
+        let mut pixels = [255, 128, 0, 128, 200, 100, 50, 0];
+        premultiply_u8(&mut pixels, 4, false);
+        assert_eq!(pixels, [128, 64, 0, 128, 0, 0, 0, 0]);
+
+        // sRGB color is scaled in linear space, so half alpha keeps more than half the sRGB value
+        let mut pixels = [255, 128, 0, 128];
+        premultiply_u8(&mut pixels, 4, true);
+        assert_eq!(pixels[1..], [93, 0, 128]);
+        assert_eq!(pixels[0], (linear_to_srgb(128.0 / 255.0) * 255.0).round() as u8);
+
+        let mut pixels = [200, 255, 200, 0];
+        premultiply_u8(&mut pixels, 2, false);
+        assert_eq!(pixels, [200, 255, 0, 0]);
+    }
+
+    #[test]
+    fn premultiply_floats() {
+        let mut pixels = [1.0, 0.5, 0.25, 0.5, 2.0, 2.0, 2.0, 0.0];
+        premultiply_f32(&mut pixels, 4);
+        assert_eq!(pixels, [0.5, 0.25, 0.125, 0.5, 0.0, 0.0, 0.0, 0.0]);
+
+        let mut pixels = [0.8, 0.25];
+        premultiply_f32(&mut pixels, 2);
+        assert_eq!(pixels, [0.2, 0.25]);
+    }
+}
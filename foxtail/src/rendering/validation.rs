@@ -0,0 +1,487 @@
+//! Offline GLSL validation through naga, so shaders can be checked in `cargo test` without a GPU.
+//! naga's GLSL frontend doesn't support everything drivers do. Most notably, any stage that uses a
+//! combined sampler like `sampler2D` is always skipped, as are geometry and tessellation stages.
+//! Skipped stages are reported with the code `SKIPPED_STAGE`, as warnings, or as errors by the `_strict` functions.
+use std::collections::HashMap;
+use std::path::Path;
+
+use naga::front::glsl::{ErrorKind, Frontend, Options};
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+
+use super::diagnostics::{Severity, ShaderDiagnostic};
+use super::shader::ShaderStage;
+
+/// `ShaderDiagnostic::code` of stages the validator couldn't check
+pub const SKIPPED_STAGE: &str = "skipped";
+
+impl ShaderStage {
+    fn to_naga(self) -> Option<naga::ShaderStage> {
+        match self {
+            Self::Vertex => Some(naga::ShaderStage::Vertex),
+            Self::Fragment => Some(naga::ShaderStage::Fragment),
+            Self::Compute => Some(naga::ShaderStage::Compute),
+            _ => None,
+        }
+    }
+}
+
+fn diagnostic(name: Option<&str>, source: &str, span: naga::Span, severity: Severity, message: String) -> ShaderDiagnostic {
+    let (line, column) = if span.is_defined() {
+        let loc = span.location(source);
+        (Some(loc.line_number as usize), Some(loc.line_position as usize))
+    } else {
+        (None, None)
+    };
+    ShaderDiagnostic {
+        file: name.map(|s| s.to_string()),
+        line,
+        column,
+        severity,
+        code: None,
+        message,
+    }
+}
+
+/// Error message including everything in the `source()` chain
+fn error_chain(err: &dyn std::error::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        message.push_str(": ");
+        message.push_str(&err.to_string());
+        source = err.source();
+    }
+    message
+}
+
+fn skipped(name: Option<&str>, source: &str, span: naga::Span, strict: bool, message: String) -> ShaderDiagnostic {
+    ShaderDiagnostic {
+        code: Some(SKIPPED_STAGE.to_string()),
+        ..diagnostic(name, source, span, if strict { Severity::Error } else { Severity::Warning }, message)
+    }
+}
+
+/// Parses and validates a stage. Returns the module if naga could fully check it.
+fn check_stage(stage: ShaderStage, source: &str, name: Option<&str>, strict: bool, diagnostics: &mut Vec<ShaderDiagnostic>) -> Option<naga::Module> {
+    let naga_stage = match stage.to_naga() {
+        Some(s) => s,
+        None => {
+            diagnostics.push(skipped(name, source, naga::Span::default(), strict, format!("{:?} stages are not supported by the offline validator", stage)));
+            return None;
+        }
+    };
+
+    let module = match Frontend::default().parse(&Options::from(naga_stage), source) {
+        Ok(module) => module,
+        Err(errors) => {
+            for err in errors {
+                if let ErrorKind::NotImplemented(what) = err.kind {
+                    diagnostics.push(skipped(name, source, err.meta, strict, format!("{} is not supported by the offline validator", what)));
+                } else {
+                    diagnostics.push(diagnostic(name, source, err.meta, Severity::Error, err.kind.to_string()));
+                }
+            }
+            return None;
+        }
+    };
+
+    // naga gives varyings without an explicit location all the same location,
+    // which the binding checks then reject. `validate_program` matches them by name instead.
+    let flags = ValidationFlags::all() - ValidationFlags::BINDINGS;
+    if let Err(err) = Validator::new(flags, Capabilities::all()).validate(&module) {
+        let span = err.spans().next().map(|(span, _)| *span).unwrap_or_default();
+        diagnostics.push(diagnostic(name, source, span, Severity::Error, error_chain(&err)));
+        return None;
+    }
+
+    Some(module)
+}
+
+/// Readable type name, comparable between modules
+fn type_name(module: &naga::Module, ty: naga::Handle<naga::Type>) -> String {
+    let scalar = |kind: naga::ScalarKind, width: u8| format!("{:?}{}", kind, width as u32 * 8).to_lowercase();
+    match &module.types[ty].inner {
+        naga::TypeInner::Scalar { kind, width } => scalar(*kind, *width),
+        naga::TypeInner::Vector { size, kind, width } => format!("vec{}<{}>", *size as u8, scalar(*kind, *width)),
+        naga::TypeInner::Matrix { columns, rows, width } => format!("mat{}x{}<{}>", *columns as u8, *rows as u8, scalar(naga::ScalarKind::Float, *width)),
+        naga::TypeInner::Array { base, size, .. } => format!("array<{}, {:?}>", type_name(module, *base), size),
+        other => format!("{:?}", other),
+    }
+}
+
+/// `(name, location, type)` of every user-defined input or output of the entry point
+fn interface(module: &naga::Module, outputs: bool) -> Vec<(Option<String>, u32, String)> {
+    let mut vars = Vec::new();
+    let mut push = |name: &Option<String>, ty: naga::Handle<naga::Type>, binding: &Option<naga::Binding>| {
+        match binding {
+            Some(naga::Binding::Location { location, .. }) => vars.push((name.clone(), *location, type_name(module, ty))),
+            Some(naga::Binding::BuiltIn(_)) => {},
+            None => {
+                if let naga::TypeInner::Struct { members, .. } = &module.types[ty].inner {
+                    for m in members {
+                        if let Some(naga::Binding::Location { location, .. }) = &m.binding {
+                            vars.push((m.name.clone(), *location, type_name(module, m.ty)));
+                        }
+                    }
+                }
+            }
+        }
+    };
+    for ep in &module.entry_points {
+        if outputs {
+            if let Some(result) = &ep.function.result {
+                push(&None, result.ty, &result.binding);
+            }
+        } else {
+            for arg in &ep.function.arguments {
+                push(&arg.name, arg.ty, &arg.binding);
+            }
+        }
+    }
+    vars
+}
+
+/// Where a global like an `in` variable was declared, naga keeps those around as globals
+fn global_span(module: &naga::Module, name: &Option<String>) -> naga::Span {
+    module.global_variables.iter()
+        .find(|(_, var)| var.name.is_some() && &var.name == name)
+        .map(|(handle, _)| module.global_variables.get_span(handle))
+        .unwrap_or_default()
+}
+
+/// How a stage input or output got its location
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Location {
+    Explicit(u32),
+    Implicit,
+    /// Not a plain declaration, like a member of an interface block or a location set through a macro
+    Unknown,
+}
+
+/// Locations of the `in` or `out` variables declared in `source`, by name. naga doesn't keep the
+/// qualifiers around (variables without one all end up at location 0), so they're read from the source.
+fn declared_locations(source: &str, storage: &str) -> HashMap<String, Location> {
+    // Top level declarations only, without preprocessor lines
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    for line in strip_comments(source).lines().filter(|l| !l.trim_start().starts_with('#')) {
+        for c in line.chars() {
+            match c {
+                '{' => depth += 1,
+                // Whatever came before a function body or interface block isn't a declaration we understand
+                '}' => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        current.clear();
+                    }
+                },
+                ';' if depth == 0 => statements.push(std::mem::take(&mut current)),
+                c if depth == 0 => current.push(c),
+                _ => {},
+            }
+        }
+        current.push(' ');
+    }
+
+    let mut locations = HashMap::new();
+    for statement in statements {
+        let (layout, rest) = match statement.find("layout") {
+            Some(start) => match statement[start..].find(')') {
+                Some(end) => (&statement[start..start + end + 1], statement[start + end + 1..].to_string()),
+                None => continue,
+            },
+            None => ("", statement.clone()),
+        };
+        let mut decls = rest.split(',');
+        let first = decls.next().unwrap_or("").split_whitespace().collect::<Vec<_>>();
+        if !first.contains(&storage) {
+            continue;
+        }
+        let location = if layout.contains("location") {
+            layout.trim_start_matches("layout").trim_matches(|c: char| c == '(' || c == ')' || c.is_whitespace())
+                .split(',')
+                .filter_map(|q| q.split_once('='))
+                .find(|(key, _)| key.trim() == "location")
+                .and_then(|(_, value)| value.trim().parse().ok())
+                .map_or(Location::Unknown, Location::Explicit)
+        } else {
+            Location::Implicit
+        };
+        let var_name = |decl: &str| decl.split(['[', '=']).next().unwrap_or("").trim().to_string();
+        if let Some(name) = first.last() {
+            locations.insert(var_name(name), location);
+        }
+        // Further variables in the same declaration follow the first one, depending on its size
+        for decl in decls {
+            let name = var_name(decl);
+            locations.insert(name, if location == Location::Implicit { Location::Implicit } else { Location::Unknown });
+        }
+    }
+    locations
+}
+
+/// Replaces comments with spaces, keeping line breaks
+fn strip_comments(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            },
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                    }
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+                out.push(' ');
+            },
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn validate_stage(stage: ShaderStage, source: &str, strict: bool) -> Result<Vec<ShaderDiagnostic>, Vec<ShaderDiagnostic>> {
+    let mut diagnostics = Vec::new();
+    check_stage(stage, source, None, strict, &mut diagnostics);
+    finish(diagnostics)
+}
+
+/// Validates a single shader stage. On success, returns any warnings. On failure, returns all diagnostics.
+/// Stages naga can't check, like anything that uses `sampler2D` or other combined samplers,
+/// are skipped with a `SKIPPED_STAGE` warning.
+pub fn validate(stage: ShaderStage, source: &str) -> Result<Vec<ShaderDiagnostic>, Vec<ShaderDiagnostic>> {
+    validate_stage(stage, source, false)
+}
+
+/// Like `validate`, but stages naga can't check are errors
+pub fn validate_strict(stage: ShaderStage, source: &str) -> Result<Vec<ShaderDiagnostic>, Vec<ShaderDiagnostic>> {
+    validate_stage(stage, source, true)
+}
+
+fn check_program(stages: &[(ShaderStage, &str, &str)], strict: bool) -> Result<Vec<ShaderDiagnostic>, Vec<ShaderDiagnostic>> {
+    let mut diagnostics = Vec::new();
+    let modules = stages.iter().map(|(stage, src, name)| (*stage, *src, *name, check_stage(*stage, src, Some(name), strict, &mut diagnostics))).collect::<Vec<_>>();
+
+    let vs = modules.iter().find(|m| m.0 == ShaderStage::Vertex);
+    let fs = modules.iter().find(|m| m.0 == ShaderStage::Fragment);
+    // Geometry and tessellation stages sit in between and aren't checked by naga
+    let has_middle_stages = stages.iter().any(|s| matches!(s.0, ShaderStage::Geometry | ShaderStage::TessControl | ShaderStage::TessEvaluation));
+    if let (Some((_, vs_src, _, Some(vs_module))), Some((_, fs_src, fs_name, Some(fs_module))), false) = (vs, fs, has_middle_stages) {
+        let outputs = interface(vs_module, true);
+        let output_locations = declared_locations(vs_src, "out");
+        let input_locations = declared_locations(fs_src, "in");
+        let location_of = |locations: &HashMap<String, Location>, name: &Option<String>| {
+            name.as_ref().and_then(|name| locations.get(name).copied()).unwrap_or(Location::Unknown)
+        };
+        let any_output_unknown = outputs.iter().any(|o| location_of(&output_locations, &o.0) == Location::Unknown);
+        for (name, location, ty) in interface(fs_module, false) {
+            // GL matches by location if both sides have an explicit one, by name otherwise
+            let by_name = outputs.iter().find(|o| o.0.is_some() && o.0 == name);
+            let output = match location_of(&input_locations, &name) {
+                Location::Explicit(location) => outputs.iter().find(|o| location_of(&output_locations, &o.0) == Location::Explicit(location)).or(by_name),
+                Location::Implicit => by_name,
+                // Without knowing how the input is matched, a missing output can't be told apart from one at another location
+                Location::Unknown if by_name.is_none() => continue,
+                Location::Unknown => by_name,
+            };
+            let display_name = name.clone().unwrap_or_else(|| format!("location {}", location));
+            let span = global_span(fs_module, &name);
+            match output {
+                None if any_output_unknown => {},
+                None => diagnostics.push(diagnostic(Some(fs_name), fs_src, span, Severity::Error, format!("Fragment input `{}` is not written by the vertex stage", display_name))),
+                Some(output) if output.2 != ty => diagnostics.push(diagnostic(Some(fs_name), fs_src, span, Severity::Error, format!("Fragment input `{}` is {}, but the vertex stage writes {}", display_name, ty, output.2))),
+                _ => {},
+            }
+        }
+    }
+
+    finish(diagnostics)
+}
+
+/// Validates every stage of a program from `(stage, source, name)` triples, like `ShaderBuilder` takes them.
+/// Also checks that every fragment input is written by the vertex stage with a matching type.
+/// Stages are skipped like in `validate`, and the interface check only runs if both stages were checked.
+/// Inputs whose location can't be read from the source are only matched by name.
+pub fn validate_program(stages: &[(ShaderStage, &str, &str)]) -> Result<Vec<ShaderDiagnostic>, Vec<ShaderDiagnostic>> {
+    check_program(stages, false)
+}
+
+/// Like `validate_program`, but stages naga can't check are errors
+pub fn validate_program_strict(stages: &[(ShaderStage, &str, &str)]) -> Result<Vec<ShaderDiagnostic>, Vec<ShaderDiagnostic>> {
+    check_program(stages, true)
+}
+
+fn finish(diagnostics: Vec<ShaderDiagnostic>) -> Result<Vec<ShaderDiagnostic>, Vec<ShaderDiagnostic>> {
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        Err(diagnostics)
+    } else {
+        Ok(diagnostics)
+    }
+}
+
+fn check_program_files<P: AsRef<Path>>(stages: &[(ShaderStage, P)], strict: bool) {
+    let sources = stages.iter().map(|(stage, path)| {
+        let path = path.as_ref();
+        println!("cargo:rerun-if-changed={}", path.display());
+        let src = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("Failed to read shader `{}`: {}", path.display(), e));
+        (*stage, src, path.display().to_string())
+    }).collect::<Vec<_>>();
+    let stages = sources.iter().map(|(stage, src, name)| (*stage, src.as_str(), name.as_str())).collect::<Vec<_>>();
+
+    match check_program(&stages, strict) {
+        Ok(warnings) => {
+            for warning in warnings {
+                println!("cargo:warning={}", warning);
+            }
+        },
+        Err(diagnostics) => {
+            let errors = diagnostics.iter().map(|d| d.to_string()).collect::<Vec<String>>().join("\n");
+            panic!("Shader validation failed!\n{}", errors);
+        }
+    }
+}
+
+/// For use in build scripts. Validates a program from `(stage, path)` pairs like `validate_program`,
+/// printing warnings through cargo and failing the build on errors.
+pub fn validate_program_files<P: AsRef<Path>>(stages: &[(ShaderStage, P)]) {
+    check_program_files(stages, false)
+}
+
+/// Like `validate_program_files`, but stages naga can't check fail the build
+pub fn validate_program_files_strict<P: AsRef<Path>>(stages: &[(ShaderStage, P)]) {
+    check_program_files(stages, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VS: &str = "#version 450
+layout(location = 0) in vec3 position;
+layout(location = 0) out vec2 v_uv;
+layout(location = 1) out vec3 v_normal;
+void main() {
+    v_uv = position.xy;
+    v_normal = position;
+    gl_Position = vec4(position, 1.0);
+}
+";
+
+    const FS: &str = "#version 450
+layout(location = 0) in vec2 v_uv;
+layout(location = 1) in vec3 v_normal;
+layout(location = 0) out vec4 color;
+void main() {
+    color = vec4(v_uv, v_normal.x, 1.0);
+}
+";
+
+    #[test]
+    fn good_stage() {
+        assert_eq!(validate_strict(ShaderStage::Vertex, VS), Ok(Vec::new()));
+        assert_eq!(validate_strict(ShaderStage::Fragment, FS), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn bad_stage() {
+        let errors = validate(ShaderStage::Fragment, "#version 450\nvoid main() { undefined = 1.0; }\n").unwrap_err();
+        assert!(errors.iter().all(|d| d.severity == Severity::Error));
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    fn good_program() {
+        assert_eq!(validate_program_strict(&[(ShaderStage::Vertex, VS, "vs"), (ShaderStage::Fragment, FS, "fs")]), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn mismatched_program() {
+        let fs = FS.replace("in vec2 v_uv", "in vec3 v_uv").replace("vec4(v_uv, v_normal.x, 1.0)", "vec4(v_uv, 1.0)");
+        let errors = validate_program_strict(&[(ShaderStage::Vertex, VS, "vs"), (ShaderStage::Fragment, &fs, "fs")]).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Fragment input `v_uv` is vec3<float32>, but the vertex stage writes vec2<float32>");
+        assert_eq!(errors[0].line, Some(2));
+    }
+
+    #[test]
+    fn implicit_locations_match_by_name() {
+        // The comment mentions location on the declaration line, it must not count as explicit
+        let vs = "#version 450
+layout(location = 0) in vec3 position;
+out vec3 a; // no location
+out vec2 b;
+void main() {
+    a = position;
+    b = position.xy;
+    gl_Position = vec4(position, 1.0);
+}
+";
+        let fs = "#version 450
+in vec2 b; // no location
+in vec3 a;
+layout(location = 0) out vec4 color;
+void main() {
+    color = vec4(a, b.x);
+}
+";
+        assert_eq!(validate_program_strict(&[(ShaderStage::Vertex, vs, "vs"), (ShaderStage::Fragment, fs, "fs")]), Ok(Vec::new()));
+        let fs = fs.replace("in vec3 a", "in vec3 c").replace("vec4(a, b.x)", "vec4(c, b.x)");
+        let errors = validate_program_strict(&[(ShaderStage::Vertex, vs, "vs"), (ShaderStage::Fragment, &fs, "fs")]).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Fragment input `c` is not written by the vertex stage");
+    }
+
+    #[test]
+    fn explicit_locations_match_by_location() {
+        let fs = FS.replace("v_uv", "uv").replace("v_normal", "normal");
+        assert_eq!(validate_program_strict(&[(ShaderStage::Vertex, VS, "vs"), (ShaderStage::Fragment, &fs, "fs")]), Ok(Vec::new()));
+        let fs = fs.replace("location = 1) in vec3", "location = 2) in vec3");
+        let errors = validate_program_strict(&[(ShaderStage::Vertex, VS, "vs"), (ShaderStage::Fragment, &fs, "fs")]).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Fragment input `normal` is not written by the vertex stage");
+    }
+
+    #[test]
+    fn unknown_locations_are_skipped() {
+        let fs = FS.replace("#version 450\n", "#version 450\n#define NORMAL_LOCATION 1\n")
+            .replace("location = 1) in vec3 v_normal", "location = NORMAL_LOCATION) in vec3 normal")
+            .replace("v_normal.x", "normal.x");
+        assert_eq!(declared_locations(&fs, "in").get("v_uv"), Some(&Location::Explicit(0)));
+        assert_eq!(declared_locations(&fs, "in").get("normal"), Some(&Location::Unknown));
+        assert_eq!(validate_program_strict(&[(ShaderStage::Vertex, VS, "vs"), (ShaderStage::Fragment, &fs, "fs")]), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn skipped_stages() {
+        let fs = "#version 450
+layout(binding = 0) uniform sampler2D tex;
+layout(location = 0) out vec4 color;
+void main() {
+    color = texture(tex, vec2(0.5));
+}
+";
+        let warnings = validate(ShaderStage::Fragment, fs).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].severity, Severity::Warning);
+        assert_eq!(warnings[0].code.as_deref(), Some(SKIPPED_STAGE));
+
+        let errors = validate_strict(ShaderStage::Fragment, fs).unwrap_err();
+        assert_eq!(errors[0].severity, Severity::Error);
+        assert_eq!(errors[0].code.as_deref(), Some(SKIPPED_STAGE));
+
+        let errors = validate_strict(ShaderStage::Geometry, "#version 450\nvoid main() {}\n").unwrap_err();
+        assert_eq!(errors[0].code.as_deref(), Some(SKIPPED_STAGE));
+    }
+}
@@ -0,0 +1,204 @@
+//! Describes how vertex data is laid out in buffers, and how each attribute reaches the shader.
+//! ```ignore
+//! #[repr(C)]
+//! #[derive(Clone, Copy)]
+//! struct SkinnedVertex {
+//!     position: [f32; 3],
+//!     color: [u8; 4],
+//!     bones: [u16; 4],
+//! }
+//!
+//! unsafe impl Vertex for SkinnedVertex {
+//!     fn layout() -> VertexStream {
+//!         VertexStream::new(std::mem::size_of::<Self>())
+//!             .float(0, AttribType::F32, 3, std::mem::offset_of!(Self, position))
+//!             .normalized(1, AttribType::U8, 4, std::mem::offset_of!(Self, color))
+//!             .integer(2, AttribType::U16, 4, std::mem::offset_of!(Self, bones))
+//!     }
+//! }
+//! ```
+use glow::*;
+
+/// Component type of an attribute as stored in the buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttribType {
+    F32,
+    /// Half floats, stored as `u16` bits
+    F16,
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+}
+
+impl AttribType {
+    pub(crate) fn to_gl(self) -> u32 {
+        match self {
+            Self::F32 => FLOAT,
+            Self::F16 => HALF_FLOAT,
+            Self::U8 => UNSIGNED_BYTE,
+            Self::I8 => BYTE,
+            Self::U16 => UNSIGNED_SHORT,
+            Self::I16 => SHORT,
+            Self::U32 => UNSIGNED_INT,
+            Self::I32 => INT,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Self::U8 | Self::I8 => 1,
+            Self::F16 | Self::U16 | Self::I16 => 2,
+            Self::F32 | Self::U32 | Self::I32 => 4,
+        }
+    }
+
+    fn is_float(&self) -> bool {
+        matches!(self, Self::F32 | Self::F16)
+    }
+}
+
+/// How the shader sees an attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttribMode {
+    /// `float`/`vecN` inputs, integers are converted as is
+    Float,
+    /// `float`/`vecN` inputs, integers are mapped to `[0, 1]` (unsigned) or `[-1, 1]` (signed)
+    Normalized,
+    /// `int`/`uint`/`ivecN`/`uvecN` inputs
+    Integer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub ty: AttribType,
+    /// 1 to 4
+    pub components: u8,
+    pub mode: AttribMode,
+    /// Byte offset from the start of each vertex
+    pub offset: usize,
+}
+
+/// Attributes read from a single buffer, interleaved with `stride` bytes per vertex
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexStream {
+    pub stride: usize,
+    pub attributes: Vec<VertexAttribute>,
+    /// 0 advances per vertex, `n` advances once every `n` instances
+    pub divisor: u32,
+}
+
+impl VertexStream {
+    pub fn new(stride: usize) -> Self {
+        Self {
+            stride,
+            attributes: Vec::new(),
+            divisor: 0,
+        }
+    }
+
+    /// A stream that advances once per instance
+    pub fn per_instance(stride: usize) -> Self {
+        Self::new(stride).divisor(1)
+    }
+
+    pub fn divisor(mut self, divisor: u32) -> Self {
+        self.divisor = divisor;
+        self
+    }
+
+    pub fn is_per_instance(&self) -> bool {
+        self.divisor != 0
+    }
+
+    /// Panics if the attribute doesn't make sense, or doesn't fit in the stride!
+    pub fn attribute(mut self, location: u32, ty: AttribType, components: u8, mode: AttribMode, offset: usize) -> Self {
+        if components == 0 || components > 4 {
+            panic!("Vertex attribute {} has {} components, it needs 1 to 4!", location, components);
+        }
+        if ty.is_float() && mode != AttribMode::Float {
+            panic!("Vertex attribute {} is a float type, it can't be {:?}!", location, mode);
+        }
+        if offset + ty.size() * components as usize > self.stride {
+            panic!("Vertex attribute {} doesn't fit in a stride of {} bytes!", location, self.stride);
+        }
+        self.attributes.push(VertexAttribute { location, ty, components, mode, offset });
+        self
+    }
+
+    pub fn float(self, location: u32, ty: AttribType, components: u8, offset: usize) -> Self {
+        self.attribute(location, ty, components, AttribMode::Float, offset)
+    }
+
+    pub fn normalized(self, location: u32, ty: AttribType, components: u8, offset: usize) -> Self {
+        self.attribute(location, ty, components, AttribMode::Normalized, offset)
+    }
+
+    pub fn integer(self, location: u32, ty: AttribType, components: u8, offset: usize) -> Self {
+        self.attribute(location, ty, components, AttribMode::Integer, offset)
+    }
+
+    /// Sets up the attributes for the buffer currently bound to `ARRAY_BUFFER`
+    pub(crate) unsafe fn apply(&self, gl: &Context) {
+        for attrib in &self.attributes {
+            gl.enable_vertex_attrib_array(attrib.location);
+            match attrib.mode {
+                AttribMode::Integer => gl.vertex_attrib_pointer_i32(attrib.location, attrib.components as i32, attrib.ty.to_gl(), self.stride as i32, attrib.offset as i32),
+                mode => gl.vertex_attrib_pointer_f32(attrib.location, attrib.components as i32, attrib.ty.to_gl(), mode == AttribMode::Normalized, self.stride as i32, attrib.offset as i32),
+            }
+            gl.vertex_attrib_divisor(attrib.location, self.divisor);
+        }
+    }
+}
+
+/// One stream per vertex buffer. A single stream is interleaved, several are separate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexLayout {
+    pub streams: Vec<VertexStream>,
+}
+
+impl VertexLayout {
+    pub fn interleaved(stream: VertexStream) -> Self {
+        Self { streams: vec![stream] }
+    }
+
+    pub fn separate(streams: Vec<VertexStream>) -> Self {
+        Self { streams }
+    }
+}
+
+/// A vertex type that can be uploaded as is.
+///
+/// # Safety
+/// Vertex slices get uploaded as raw bytes, and the GPU reads them back through `layout()`. Implementors must:
+/// - be `#[repr(C)]`, so the fields sit at the offsets `layout()` gives them
+/// - have no padding bytes, they are uninitialized and must not end up in an attribute
+/// - return a `layout()` with a stride of `size_of::<Self>()`, and attributes whose offset plus size stay within it
+pub unsafe trait Vertex: Copy {
+    fn layout() -> VertexStream;
+}
+
+/// What `Mesh::quad` and `Mesh::from_verts_indices` use. Position at location 0, color at 1, uv at 2.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DefaultVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+unsafe impl Vertex for DefaultVertex {
+    fn layout() -> VertexStream {
+        VertexStream::new(std::mem::size_of::<Self>())
+            .float(0, AttribType::F32, 3, std::mem::offset_of!(Self, position))
+            .float(1, AttribType::F32, 3, std::mem::offset_of!(Self, color))
+            .float(2, AttribType::F32, 2, std::mem::offset_of!(Self, uv))
+    }
+}
+
+pub(crate) fn slice_as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+    unsafe { core::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
+}
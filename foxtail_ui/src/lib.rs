@@ -7,6 +7,7 @@ use winit::window::Window;
 use winit::event::WindowEvent;
 use glow::Context;
 
+pub use egui;
 pub use egui::Context as EguiContext;
 
 pub struct FoxUi {